@@ -0,0 +1,879 @@
+//! Native launch backend: start the sandboxed command as a transient unit by
+//! calling `org.freedesktop.systemd1.Manager.StartTransientUnit` on the user
+//! bus, instead of exec'ing `systemd-run`.
+//!
+//! The unit gets exactly the properties `Config::properties` renders for
+//! `systemd-run`. Each `Name=value` assignment is converted here into the
+//! typed D-Bus value systemd expects, the same way `systemd-run` does
+//! internally. Afterwards playpen stays attached to the bus, waits for the
+//! unit to stop and reports how its main process ended.
+
+use crate::size::{parse_bytes, parse_rate};
+use crate::Config;
+use anyhow::{anyhow, bail, Context, Result};
+use nix::sys::signal::{SigSet, Signal};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::io::{PipeReader, Read, Write};
+use std::net::IpAddr;
+use std::os::fd::AsFd;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+use zbus::blocking::fdo::PropertiesProxy;
+use zbus::blocking::{Connection, Proxy};
+use zbus::proxy::CacheProperties;
+use zbus::zvariant::{Fd, OwnedObjectPath, Value};
+
+const DESTINATION: &str = "org.freedesktop.systemd1";
+const MANAGER_PATH: &str = "/org/freedesktop/systemd1";
+const MANAGER_INTERFACE: &str = "org.freedesktop.systemd1.Manager";
+const UNIT_INTERFACE: &str = "org.freedesktop.systemd1.Unit";
+const SERVICE_INTERFACE: &str = "org.freedesktop.systemd1.Service";
+
+/// `si_code` values systemd reports in `ExecMainCode` (see `waitid(2)`).
+const CLD_EXITED: i32 = 1;
+const CLD_KILLED: i32 = 2;
+const CLD_DUMPED: i32 = 3;
+
+/// How often to sample a unit with accounting on. systemd keeps the memory,
+/// CPU and IO totals after the unit stops, but has no peak task count or
+/// peak rates, so those are tracked here while the unit runs.
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The span over which a peak CPU or IO rate is averaged, so one busy
+/// sample does not dominate.
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Below this share of one core, a unit counts as not using CPU for
+/// `--idle-timeout`; a process blocked on a hung read still wakes up now
+/// and then.
+const IDLE_CPU_SHARE: f64 = 0.01;
+
+/// How long to wait, once the unit has stopped, for the last of its output
+/// to be relayed.
+const RELAY_DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// IO priority classes from `linux/ioprio.h`, as `IOSchedulingClass=`
+/// takes them over D-Bus.
+const IOPRIO_CLASS_RT: i32 = 1;
+const IOPRIO_CLASS_BE: i32 = 2;
+const IOPRIO_CLASS_IDLE: i32 = 3;
+
+/// `MS_REC`: bind mounts are recursive unless marked otherwise, matching
+/// how `systemd-run -pBindPaths=` sends them.
+const MS_REC: u64 = 0x4000;
+
+/// Exit code when the unit hit its `RuntimeMaxSec=` (`Result=timeout`) or
+/// `--idle-timeout` stopped it, the same code coreutils `timeout` uses.
+pub(crate) const EXIT_TIMEOUT: i32 = 124;
+/// Exit code when the kernel or systemd-oomd killed the unit for exceeding
+/// its memory limit (`Result=oom-kill`).
+pub(crate) const EXIT_OOM_KILLED: i32 = 123;
+/// Exit code when the sandbox itself failed: the unit could not be created
+/// or started, or systemd stopped it for a reason other than the command's
+/// own exit, a signal, a timeout or an OOM kill.
+pub(crate) const EXIT_SANDBOX_FAILURE: i32 = 125;
+
+/// How the unit's main process ended, read from the unit once it stopped.
+#[derive(Debug)]
+pub(crate) struct Outcome {
+    /// The service `Result=` value, e.g. `success`, `exit-code`, `oom-kill`,
+    /// or `idle-timeout` if playpen's own idle watchdog stopped the unit.
+    pub result: String,
+    /// `ExecMainCode`: one of the `CLD_*` codes.
+    pub code: i32,
+    /// `ExecMainStatus`: exit status, or signal number if killed.
+    pub status: i32,
+    pub stats: Stats,
+    /// Peak rates seen while the unit ran; empty unless accounting was on.
+    pub peaks: Peaks,
+    /// Wall time from starting the unit until it stopped.
+    pub elapsed: Duration,
+}
+
+/// The highest rates seen while sampling a running unit. A rate needs at
+/// least `RATE_WINDOW` of samples, so a shorter run has none.
+#[derive(Debug, Default)]
+pub(crate) struct Peaks {
+    pub tasks: Option<u64>,
+    /// CPU time used per second of wall time, in nanoseconds; 1e9 is one
+    /// fully busy core.
+    pub cpu_nsec_per_sec: Option<u64>,
+    pub io_read_bytes_per_sec: Option<u64>,
+    pub io_write_bytes_per_sec: Option<u64>,
+}
+
+/// Resource usage counters read from the unit after it stopped. A counter
+/// is `None` when accounting for it is off or the manager does not know it.
+#[derive(Debug, Default, Serialize)]
+pub(crate) struct Stats {
+    pub memory_peak_bytes: Option<u64>,
+    pub cpu_usage_nsec: Option<u64>,
+    pub io_read_bytes: Option<u64>,
+    pub io_write_bytes: Option<u64>,
+    /// Highest `TasksCurrent` seen while sampling; only sampled when the
+    /// unit has task accounting on.
+    pub tasks_peak: Option<u64>,
+}
+
+impl Stats {
+    /// The `--stats` text report: one `playpen:` line per counter.
+    pub fn to_text(&self) -> String {
+        let show = |v: Option<u64>, render: fn(u64) -> String| {
+            v.map(render).unwrap_or_else(|| "n/a".to_string())
+        };
+        format!(
+            "playpen: memory peak: {}\n\
+             playpen: CPU time: {}\n\
+             playpen: IO read: {}\n\
+             playpen: IO write: {}\n\
+             playpen: tasks peak: {}\n",
+            show(self.memory_peak_bytes, format_bytes),
+            show(self.cpu_usage_nsec, |ns| format!("{:.3}s", ns as f64 / 1e9)),
+            show(self.io_read_bytes, format_bytes),
+            show(self.io_write_bytes, format_bytes),
+            show(self.tasks_peak, |n| n.to_string()),
+        )
+    }
+}
+
+impl Outcome {
+    /// The exit code playpen itself should return. A limit enforced by the
+    /// sandbox gets its own code so callers can tell it apart from the
+    /// command failing; otherwise the command's status passes through, with
+    /// the shell convention of 128 + signal number for a killed process.
+    pub fn exit_code(&self) -> i32 {
+        match (self.result.as_str(), self.code) {
+            ("oom-kill", _) => EXIT_OOM_KILLED,
+            ("timeout" | "idle-timeout", _) => EXIT_TIMEOUT,
+            ("success" | "exit-code", CLD_EXITED) => self.status,
+            ("signal" | "core-dump", CLD_KILLED | CLD_DUMPED) => 128 + self.status,
+            ("success", _) => 0,
+            _ => EXIT_SANDBOX_FAILURE,
+        }
+    }
+}
+
+impl std::fmt::Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "result: {}, main process: ", self.result)?;
+        let signal = || {
+            Signal::try_from(self.status)
+                .map(|s| s.as_str().to_string())
+                .unwrap_or_else(|_| format!("signal {}", self.status))
+        };
+        match self.code {
+            CLD_EXITED => write!(f, "exited with status {}", self.status)?,
+            CLD_KILLED => write!(f, "killed by {}", signal())?,
+            CLD_DUMPED => write!(f, "dumped core on {}", signal())?,
+            _ => write!(f, "not run")?,
+        }
+        if let Some(peak) = self.stats.memory_peak_bytes {
+            write!(f, ", memory peak: {}", format_bytes(peak))?;
+        }
+        Ok(())
+    }
+}
+
+/// Render a byte count the way systemd does: base-1024 with one decimal.
+pub(crate) fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["B", "K", "M", "G", "T", "P"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{}B", bytes)
+    } else {
+        format!("{:.1}{}", value, UNITS[unit])
+    }
+}
+
+/// Run `command` in a transient service on the user manager, with the
+/// `NAME=VALUE` assignments in `env`, and wait for it to finish. The command
+/// inherits playpen's stdin, stdout and stderr. With an `idle_timeout`, its
+/// output is relayed through pipes instead, so the unit can be stopped once it
+/// goes quiet and stops using CPU.
+pub(crate) fn run(
+    config: &Config,
+    env: &[String],
+    command: &[String],
+    idle_timeout: Option<Duration>,
+) -> Result<Outcome> {
+    let (program, _) = command.split_first().context("no command given")?;
+    let exec_path = find_executable(program, env)?;
+
+    // Block the termination signals before the bus connection spawns its
+    // threads, so they are all delivered to the forwarding thread below.
+    let mut signals = SigSet::empty();
+    signals.add(Signal::SIGINT);
+    signals.add(Signal::SIGTERM);
+    signals.add(Signal::SIGHUP);
+    signals.thread_block()?;
+
+    let conn = Connection::session().context("failed to connect to the user bus")?;
+    let manager = Proxy::new(&conn, DESTINATION, MANAGER_PATH, MANAGER_INTERFACE)?;
+    // Without a subscriber systemd does not emit unit PropertiesChanged.
+    manager.call::<_, _, ()>("Subscribe", &())?;
+
+    let name = format!(
+        "playpen-{}-{}.service",
+        std::process::id(),
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_micros())
+            .unwrap_or(0)
+    );
+
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    let stderr = std::io::stderr();
+    let relay_pipes = match idle_timeout {
+        Some(_) => Some([std::io::pipe()?, std::io::pipe()?]),
+        None => None,
+    };
+    let (out_fd, err_fd) = match &relay_pipes {
+        Some([(_, out), (_, err)]) => (out.as_fd(), err.as_fd()),
+        None => (stdout.as_fd(), stderr.as_fd()),
+    };
+    let mut properties: Vec<(String, Value)> = vec![
+        (
+            "Description".into(),
+            Value::from(format!("playpen: {}", command.join(" "))),
+        ),
+        ("Type".into(), Value::from("exec")),
+        // Keep the unit around until we have read its result, then let
+        // systemd garbage-collect it even if it failed.
+        ("AddRef".into(), Value::from(true)),
+        ("CollectMode".into(), Value::from("inactive-or-failed")),
+        (
+            "ExecStart".into(),
+            Value::from(vec![(
+                exec_path.display().to_string(),
+                command.to_vec(),
+                false,
+            )]),
+        ),
+        ("Environment".into(), Value::from(env.to_vec())),
+        (
+            "StandardInputFileDescriptor".into(),
+            Value::from(Fd::from(stdin.as_fd())),
+        ),
+        (
+            "StandardOutputFileDescriptor".into(),
+            Value::from(Fd::from(out_fd)),
+        ),
+        (
+            "StandardErrorFileDescriptor".into(),
+            Value::from(Fd::from(err_fd)),
+        ),
+    ];
+    if let Ok(cwd) = std::env::current_dir() {
+        properties.push((
+            "WorkingDirectory".into(),
+            Value::from(cwd.display().to_string()),
+        ));
+    }
+    for (_, assignment) in config.properties() {
+        properties.push(typed_property(&assignment)?);
+    }
+
+    let aux: Vec<(&str, Vec<(&str, Value)>)> = Vec::new();
+    let started = Instant::now();
+    manager
+        .call::<_, _, OwnedObjectPath>(
+            "StartTransientUnit",
+            &(name.as_str(), "fail", properties, aux),
+        )
+        .context("StartTransientUnit failed")?;
+
+    // Milliseconds after `started` at which the unit last wrote output.
+    let last_output = Arc::new(AtomicU64::new(0));
+    let (relayed_tx, relayed_rx) = mpsc::channel();
+    let mut relays = 0;
+    if let Some([(out, out_w), (err, err_w)]) = relay_pipes {
+        // systemd holds its own copies of the write ends; drop ours so the
+        // relays see end-of-file once the unit's side is closed.
+        drop((out_w, err_w));
+        relay_output(out, std::io::stdout(), started, &last_output, &relayed_tx);
+        relay_output(err, std::io::stderr(), started, &last_output, &relayed_tx);
+        relays = 2;
+    }
+
+    let forward_conn = conn.clone();
+    let forward_name = name.clone();
+    std::thread::spawn(move || {
+        if signals.wait().is_ok() {
+            stop_unit(&forward_conn, &forward_name);
+        }
+    });
+
+    let unit_path: OwnedObjectPath = manager.call("GetUnit", &(name.as_str(),))?;
+    let stopped = Arc::new(AtomicBool::new(false));
+    let sampler = config
+        .accounting
+        .then(|| sample_peaks(conn.clone(), unit_path.clone(), stopped.clone()));
+    let watchdog = idle_timeout.map(|limit| IdleWatchdog {
+        conn: conn.clone(),
+        unit_path: unit_path.clone(),
+        name: name.clone(),
+        limit,
+        started,
+        last_output: last_output.clone(),
+        stopped: stopped.clone(),
+    });
+    let watchdog = watchdog.map(IdleWatchdog::spawn);
+    wait_until_stopped(&conn, &unit_path)?;
+    let elapsed = started.elapsed();
+    stopped.store(true, Ordering::Relaxed);
+    let peaks = sampler.and_then(|s| s.join().ok()).unwrap_or_default();
+    let idle_fired = watchdog.is_some_and(|w| w.join().unwrap_or(false));
+
+    // Let the relays pass on whatever the unit wrote before it stopped.
+    let deadline = Instant::now() + RELAY_DRAIN_TIMEOUT;
+    for _ in 0..relays {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if relayed_rx.recv_timeout(left).is_err() {
+            break;
+        }
+    }
+
+    let service = uncached_proxy(&conn, &unit_path, SERVICE_INTERFACE)?;
+    // Older managers lack some counters; u64::MAX means "not tracked".
+    let counter = |name: &str| {
+        service
+            .get_property::<u64>(name)
+            .ok()
+            .filter(|&v| v != u64::MAX)
+    };
+    let mut result: String = service.get_property("Result")?;
+    if idle_fired {
+        result = "idle-timeout".to_string();
+    }
+    Ok(Outcome {
+        result,
+        code: service.get_property("ExecMainCode")?,
+        status: service.get_property("ExecMainStatus")?,
+        stats: Stats {
+            memory_peak_bytes: counter("MemoryPeak"),
+            cpu_usage_nsec: counter("CPUUsageNSec"),
+            io_read_bytes: counter("IOReadBytes"),
+            io_write_bytes: counter("IOWriteBytes"),
+            tasks_peak: peaks.tasks,
+        },
+        peaks,
+        elapsed,
+    })
+}
+
+/// Ask the manager to stop the unit, as on Ctrl-C. Errors are ignored: the
+/// unit may already be gone.
+fn stop_unit(conn: &Connection, name: &str) {
+    if let Ok(manager) = Proxy::new(conn, DESTINATION, MANAGER_PATH, MANAGER_INTERFACE) {
+        let _ = manager.call::<_, _, OwnedObjectPath>("StopUnit", &(name, "replace"));
+    }
+}
+
+/// Copy everything the unit writes to `pipe` on to `sink`, noting the time
+/// of each write in `last_output`. Reports on `done` once the unit has
+/// closed its end.
+fn relay_output(
+    mut pipe: PipeReader,
+    mut sink: impl Write + Send + 'static,
+    started: Instant,
+    last_output: &Arc<AtomicU64>,
+    done: &mpsc::Sender<()>,
+) {
+    let last_output = last_output.clone();
+    let done = done.clone();
+    std::thread::spawn(move || {
+        let mut buf = [0u8; 8192];
+        while let Ok(n) = pipe.read(&mut buf) {
+            if n == 0 {
+                break;
+            }
+            last_output.store(started.elapsed().as_millis() as u64, Ordering::Relaxed);
+            if sink
+                .write_all(&buf[..n])
+                .and_then(|_| sink.flush())
+                .is_err()
+            {
+                break;
+            }
+        }
+        let _ = done.send(());
+    });
+}
+
+/// Stops the unit once it has gone `limit` without writing output or using
+/// more than `IDLE_CPU_SHARE` of a core.
+struct IdleWatchdog {
+    conn: Connection,
+    unit_path: OwnedObjectPath,
+    name: String,
+    limit: Duration,
+    started: Instant,
+    last_output: Arc<AtomicU64>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl IdleWatchdog {
+    /// Watch until the unit stops; the thread returns whether it fired.
+    fn spawn(self) -> std::thread::JoinHandle<bool> {
+        std::thread::spawn(move || {
+            let service = uncached_proxy(&self.conn, &self.unit_path, SERVICE_INTERFACE).ok();
+            let read_cpu = || {
+                service
+                    .as_ref()?
+                    .get_property::<u64>("CPUUsageNSec")
+                    .ok()
+                    .filter(|&v| v != u64::MAX)
+            };
+            let mut last_cpu = (Instant::now(), read_cpu());
+            let mut last_busy = self.started;
+            loop {
+                std::thread::sleep(SAMPLE_INTERVAL);
+                if self.stopped.load(Ordering::Relaxed) {
+                    return false;
+                }
+                let now = Instant::now();
+                let cpu = read_cpu();
+                if let (Some(old), Some(new)) = (last_cpu.1, cpu) {
+                    let budget = (now - last_cpu.0).as_nanos() as f64 * IDLE_CPU_SHARE;
+                    if new.saturating_sub(old) as f64 > budget {
+                        last_busy = now;
+                    }
+                }
+                last_cpu = (now, cpu);
+                let wrote_at =
+                    self.started + Duration::from_millis(self.last_output.load(Ordering::Relaxed));
+                if now.duration_since(last_busy.max(wrote_at)) >= self.limit {
+                    stop_unit(&self.conn, &self.name);
+                    return true;
+                }
+            }
+        })
+    }
+}
+
+/// Poll the unit's counters until `stopped` is set, tracking the highest
+/// task count and the highest CPU and IO rates over any `RATE_WINDOW`.
+/// Always takes at least one sample.
+fn sample_peaks(
+    conn: Connection,
+    unit_path: OwnedObjectPath,
+    stopped: Arc<AtomicBool>,
+) -> std::thread::JoinHandle<Peaks> {
+    std::thread::spawn(move || {
+        let mut peaks = Peaks::default();
+        let Ok(service) = uncached_proxy(&conn, &unit_path, SERVICE_INTERFACE) else {
+            return peaks;
+        };
+        let read = |name: &str| {
+            service
+                .get_property::<u64>(name)
+                .ok()
+                .filter(|&v| v != u64::MAX)
+        };
+        // Earlier samples of the three cumulative counters, oldest first.
+        let mut window: VecDeque<(Instant, [Option<u64>; 3])> = VecDeque::new();
+        loop {
+            let now = Instant::now();
+            if let Some(n) = read("TasksCurrent") {
+                peaks.tasks = peaks.tasks.max(Some(n));
+            }
+            let counters = [
+                read("CPUUsageNSec"),
+                read("IOReadBytes"),
+                read("IOWriteBytes"),
+            ];
+            while let Some(&(then, old)) = window.front() {
+                let span = now - then;
+                if span < RATE_WINDOW {
+                    break;
+                }
+                let rates = [
+                    &mut peaks.cpu_nsec_per_sec,
+                    &mut peaks.io_read_bytes_per_sec,
+                    &mut peaks.io_write_bytes_per_sec,
+                ];
+                for ((peak, old), new) in rates.into_iter().zip(old).zip(counters) {
+                    if let (Some(old), Some(new)) = (old, new) {
+                        let rate = new.saturating_sub(old) as f64 / span.as_secs_f64();
+                        *peak = (*peak).max(Some(rate as u64));
+                    }
+                }
+                window.pop_front();
+            }
+            window.push_back((now, counters));
+            if stopped.load(Ordering::Relaxed) {
+                return peaks;
+            }
+            std::thread::sleep(SAMPLE_INTERVAL);
+        }
+    })
+}
+
+/// Block until the unit has no pending job and is `inactive` or `failed`.
+fn wait_until_stopped(conn: &Connection, unit_path: &OwnedObjectPath) -> Result<()> {
+    let unit = uncached_proxy(conn, unit_path, UNIT_INTERFACE)?;
+    let properties = PropertiesProxy::builder(conn)
+        .destination(DESTINATION)?
+        .path(unit_path.as_ref())?
+        .build()?;
+    // Subscribe before the first read so no transition can slip between.
+    let mut changes = properties.receive_properties_changed()?;
+    loop {
+        let (job_id, _): (u32, OwnedObjectPath) = unit.get_property("Job")?;
+        let state: String = unit.get_property("ActiveState")?;
+        if job_id == 0 && (state == "inactive" || state == "failed") {
+            return Ok(());
+        }
+        if changes.next().is_none() {
+            bail!("lost connection to the systemd user manager");
+        }
+    }
+}
+
+fn uncached_proxy<'a>(
+    conn: &Connection,
+    path: &'a OwnedObjectPath,
+    interface: &'static str,
+) -> Result<Proxy<'a>> {
+    Ok(zbus::blocking::proxy::Builder::new(conn)
+        .destination(DESTINATION)?
+        .path(path.as_ref())?
+        .interface(interface)?
+        .cache_properties(CacheProperties::No)
+        .build()?)
+}
+
+/// Resolve the program to an absolute path, as `ExecStart=` requires. A name
+/// without a slash is searched for in the `PATH` forwarded to the unit, or
+/// playpen's own `PATH` if none is forwarded.
+fn find_executable(program: &str, env: &[String]) -> Result<PathBuf> {
+    if program.contains('/') {
+        let path = std::env::current_dir()?.join(program);
+        return Ok(path);
+    }
+    let search = env
+        .iter()
+        .find_map(|assignment| assignment.strip_prefix("PATH="))
+        .map(str::to_string)
+        .or_else(|| std::env::var("PATH").ok())
+        .unwrap_or_default();
+    std::env::split_paths(&search)
+        .map(|dir| dir.join(program))
+        .find(|candidate| is_executable(candidate))
+        .ok_or_else(|| anyhow!("command not found: {}", program))
+}
+
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+// ============ Property Conversion ============
+
+/// Convert one `Name=value` assignment from `Config::properties` into the
+/// property name and typed value `StartTransientUnit` expects.
+fn typed_property(assignment: &str) -> Result<(String, Value<'static>)> {
+    let (name, value) = assignment
+        .split_once('=')
+        .ok_or_else(|| anyhow!("malformed property: {}", assignment))?;
+    let typed = match name {
+        "MemoryMax" | "MemoryHigh" | "MemoryLow" | "MemorySwapMax" => {
+            if let Some(pct) = value.strip_suffix('%') {
+                // A percentage of physical memory travels as a fraction of
+                // 2^32 under the `...Scale` property.
+                let scale = parse_percent(pct)? * f64::from(u32::MAX);
+                return Ok((format!("{}Scale", name), Value::from(scale as u32)));
+            }
+            Value::from(parse_bytes(value)?)
+        }
+        "TasksMax" => match value.strip_suffix('%') {
+            Some(pct) => {
+                let scale = parse_percent(pct)? * f64::from(u32::MAX);
+                return Ok(("TasksMaxScale".into(), Value::from(scale as u32)));
+            }
+            None if value == "infinity" => Value::from(u64::MAX),
+            None => Value::from(
+                value
+                    .parse::<u64>()
+                    .map_err(|_| anyhow!("invalid task limit: {}", value))?,
+            ),
+        },
+        "AllowedCPUs" | "AllowedMemoryNodes" => Value::from(parse_cpu_set(value)?),
+        "CPUWeight" => match value {
+            // CGROUP_WEIGHT_IDLE
+            "idle" => Value::from(0u64),
+            _ => Value::from(
+                value
+                    .parse::<u64>()
+                    .map_err(|_| anyhow!("invalid CPU weight: {}", value))?,
+            ),
+        },
+        "Nice" => Value::from(
+            value
+                .parse::<i32>()
+                .map_err(|_| anyhow!("invalid nice level: {}", value))?,
+        ),
+        "IOSchedulingClass" => Value::from(match value {
+            "realtime" => IOPRIO_CLASS_RT,
+            "best-effort" => IOPRIO_CLASS_BE,
+            "idle" => IOPRIO_CLASS_IDLE,
+            _ => bail!("invalid IO scheduling class: {}", value),
+        }),
+        "CPUQuota" => {
+            let pct = value
+                .strip_suffix('%')
+                .ok_or_else(|| anyhow!("CPUQuota must be a percentage: {}", value))?;
+            let usec = parse_percent(pct)? * 1_000_000.0;
+            return Ok(("CPUQuotaPerSecUSec".into(), Value::from(usec as u64)));
+        }
+        "RuntimeMaxSec" => {
+            return Ok(("RuntimeMaxUSec".into(), Value::from(parse_usec(value)?)));
+        }
+        "CPUQuotaPeriodSec" => {
+            return Ok(("CPUQuotaPeriodUSec".into(), Value::from(parse_usec(value)?)));
+        }
+        "IOReadBandwidthMax" | "IOWriteBandwidthMax" => {
+            let (path, rate) = value
+                .rsplit_once(' ')
+                .ok_or_else(|| anyhow!("{} needs a path and a rate: {}", name, value))?;
+            Value::from(vec![(path.to_string(), parse_rate(rate)?)])
+        }
+        "IOReadIOPSMax" | "IOWriteIOPSMax" => {
+            let (path, iops) = value
+                .rsplit_once(' ')
+                .ok_or_else(|| anyhow!("{} needs a path and a count: {}", name, value))?;
+            let iops = match iops {
+                "infinity" => u64::MAX,
+                _ => iops
+                    .parse::<u64>()
+                    .map_err(|_| anyhow!("invalid IOPS limit: {}", iops))?,
+            };
+            Value::from(vec![(path.to_string(), iops)])
+        }
+        "IOWeight" => Value::from(
+            value
+                .parse::<u64>()
+                .map_err(|_| anyhow!("invalid IO weight: {}", value))?,
+        ),
+        "PrivateTmp"
+        | "PrivateDevices"
+        | "ProtectKernelTunables"
+        | "ProtectControlGroups"
+        | "PrivateNetwork"
+        | "MemoryAccounting"
+        | "CPUAccounting"
+        | "IOAccounting"
+        | "TasksAccounting" => Value::from(parse_bool(value)?),
+        "ProtectHome" | "ProtectSystem" => Value::from(value.to_string()),
+        "IPAddressAllow" | "IPAddressDeny" => Value::from(parse_ip_prefixes(value)?),
+        "SocketBindAllow" | "SocketBindDeny" => Value::from(vec![parse_socket_bind(value)?]),
+        "BindPaths" | "BindReadOnlyPaths" => {
+            let (entry, ignore_missing) = match value.strip_prefix('-') {
+                Some(rest) => (rest, true),
+                None => (value, false),
+            };
+            // `SOURCE[:DEST[:OPTIONS]]`, the destination defaulting to the
+            // source.
+            let mut parts = entry.splitn(3, ':');
+            let source = parts.next().unwrap_or(entry);
+            let dest = parts.next().unwrap_or(source);
+            let flags = match parts.next() {
+                Some("norbind") => 0,
+                _ => MS_REC,
+            };
+            Value::from(vec![(
+                source.to_string(),
+                dest.to_string(),
+                ignore_missing,
+                flags,
+            )])
+        }
+        "InaccessiblePaths" => Value::from(vec![value.to_string()]),
+        _ => bail!("property {} is not supported by the dbus backend", name),
+    };
+    Ok((name.to_string(), typed))
+}
+
+/// A systemd time span such as `100ms`, `30m` or `1h 30m`, in
+/// microseconds. A bare number means seconds; `infinity` means no limit.
+pub(crate) fn parse_usec(value: &str) -> Result<u64> {
+    let invalid = || anyhow!("invalid time span: {}", value);
+    if value == "infinity" {
+        return Ok(u64::MAX);
+    }
+    let mut rest = value.trim();
+    if rest.is_empty() {
+        return Err(invalid());
+    }
+    let mut total = 0.0;
+    while !rest.is_empty() {
+        let split = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let number: f64 = rest[..split].parse().map_err(|_| invalid())?;
+        rest = &rest[split..];
+        let split = rest
+            .find(|c: char| c.is_ascii_digit() || c == ' ')
+            .unwrap_or(rest.len());
+        let usec_per_unit = match &rest[..split] {
+            "us" | "usec" => 1.0,
+            "ms" | "msec" => 1e3,
+            "" | "s" | "sec" | "second" | "seconds" => 1e6,
+            "m" | "min" | "minute" | "minutes" => 60e6,
+            "h" | "hr" | "hour" | "hours" => 3600e6,
+            "d" | "day" | "days" => 86400e6,
+            "w" | "week" | "weeks" => 604800e6,
+            _ => return Err(invalid()),
+        };
+        total += number * usec_per_unit;
+        rest = rest[split..].trim_start();
+    }
+    Ok(total as u64)
+}
+
+/// A CPU or NUMA node list such as `0-3,8`, as the bitmask systemd takes for
+/// `AllowedCPUs=` and `AllowedMemoryNodes=`: bit `n % 8` of byte `n / 8` is
+/// set for each listed CPU `n`.
+pub(crate) fn parse_cpu_set(value: &str) -> Result<Vec<u8>> {
+    let invalid = || anyhow!("invalid CPU list: {}", value);
+    let mut mask = Vec::new();
+    for item in value.split([',', ' ']).filter(|item| !item.is_empty()) {
+        let (first, last) = item.split_once('-').unwrap_or((item, item));
+        let first: usize = first.parse().map_err(|_| invalid())?;
+        let last: usize = last.parse().map_err(|_| invalid())?;
+        // The kernel's CONFIG_NR_CPUS tops out at 8192.
+        if first > last || last >= 8192 {
+            return Err(invalid());
+        }
+        mask.resize(mask.len().max(last / 8 + 1), 0u8);
+        for cpu in first..=last {
+            mask[cpu / 8] |= 1 << (cpu % 8);
+        }
+    }
+    if mask.is_empty() {
+        return Err(invalid());
+    }
+    Ok(mask)
+}
+
+/// The number in front of a `%` sign, as a fraction (`50` -> `0.5`).
+fn parse_percent(pct: &str) -> Result<f64> {
+    let n: f64 = pct
+        .parse()
+        .map_err(|_| anyhow!("invalid percentage: {}%", pct))?;
+    Ok(n / 100.0)
+}
+
+fn parse_bool(value: &str) -> Result<bool> {
+    match value {
+        "yes" | "true" | "on" | "1" => Ok(true),
+        "no" | "false" | "off" | "0" => Ok(false),
+        _ => bail!("invalid boolean: {}", value),
+    }
+}
+
+/// `IPAddressAllow=`/`IPAddressDeny=` entries as `(family, address, prefix)`
+/// triples. The symbolic names expand to the same ranges systemd uses.
+fn parse_ip_prefixes(value: &str) -> Result<Vec<(i32, Vec<u8>, u32)>> {
+    let cidrs: &[&str] = match value {
+        "any" => &["0.0.0.0/0", "::/0"],
+        "localhost" => &["127.0.0.0/8", "::1/128"],
+        "link-local" => &["169.254.0.0/16", "fe80::/64"],
+        "multicast" => &["224.0.0.0/4", "ff00::/8"],
+        other => &[other],
+    };
+    cidrs
+        .iter()
+        .map(|cidr| {
+            let (addr, prefix) = match cidr.split_once('/') {
+                Some((addr, prefix)) => (addr, Some(prefix)),
+                None => (*cidr, None),
+            };
+            let addr: IpAddr = addr
+                .parse()
+                .map_err(|_| anyhow!("invalid IP address: {}", value))?;
+            let (family, bytes, max_prefix) = match addr {
+                IpAddr::V4(a) => (libc_af::INET, a.octets().to_vec(), 32),
+                IpAddr::V6(a) => (libc_af::INET6, a.octets().to_vec(), 128),
+            };
+            let prefix = match prefix {
+                Some(p) => p
+                    .parse()
+                    .ok()
+                    .filter(|p| *p <= max_prefix)
+                    .ok_or_else(|| anyhow!("invalid prefix length: {}", value))?,
+                None => max_prefix,
+            };
+            Ok((family, bytes, prefix))
+        })
+        .collect()
+}
+
+/// A `SocketBindAllow=`/`SocketBindDeny=` rule
+/// (`[family:][protocol:]port[-port]` or `any`) as
+/// `(family, protocol, port count, first port)`.
+fn parse_socket_bind(value: &str) -> Result<(i32, i32, u16, u16)> {
+    if value == "any" {
+        return Ok((0, 0, 0, 0));
+    }
+    let mut family = 0;
+    let mut protocol = 0;
+    let mut rest = value;
+    if let Some((head, tail)) = rest.split_once(':') {
+        match head {
+            "ipv4" => family = libc_af::INET,
+            "ipv6" => family = libc_af::INET6,
+            _ => {}
+        }
+        if family != 0 {
+            rest = tail;
+        }
+    }
+    if let Some((head, tail)) = rest.split_once(':') {
+        protocol = match head {
+            "tcp" => 6,
+            "udp" => 17,
+            _ => bail!("invalid socket bind rule: {}", value),
+        };
+        rest = tail;
+    } else if rest == "tcp" || rest == "udp" {
+        protocol = if rest == "tcp" { 6 } else { 17 };
+        rest = "any";
+    }
+    if rest == "any" {
+        return Ok((family, protocol, 0, 0));
+    }
+    let invalid = || anyhow!("invalid socket bind rule: {}", value);
+    let (min, max): (u16, u16) = match rest.split_once('-') {
+        Some((lo, hi)) => (
+            lo.parse().map_err(|_| invalid())?,
+            hi.parse().map_err(|_| invalid())?,
+        ),
+        None => {
+            let port = rest.parse().map_err(|_| invalid())?;
+            (port, port)
+        }
+    };
+    if max < min {
+        return Err(invalid());
+    }
+    Ok((family, protocol, max - min + 1, min))
+}
+
+/// Address family numbers from `<sys/socket.h>`, which systemd uses on the
+/// wire.
+mod libc_af {
+    pub const INET: i32 = 2;
+    pub const INET6: i32 = 10;
+}
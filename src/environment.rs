@@ -0,0 +1,190 @@
+//! Which environment variables reach the sandboxed command.
+//!
+//! `--capture-env` forwards the calling environment and `--capture-path`
+//! just `PATH`. On top of that, `--env-allow` patterns forward more names
+//! and `--env-deny` patterns hold names back. Names that look like they
+//! carry credentials (`SECRET_PATTERNS`) are held back too, unless allowed
+//! by their exact name. Values from `--env-file` and `--setenv` are set as
+//! given, whatever the patterns say.
+//!
+//! Values are passed on byte for byte. systemd only carries UTF-8, so
+//! `for_systemd` sets any other value from a small `/bin/sh` wrapper around
+//! the command instead.
+
+use crate::Config;
+use std::ffi::{OsStr, OsString};
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+
+/// Names denied by default, because their values are usually credentials.
+/// An exact name in `--env-allow` still lets one through; a pattern such as
+/// `CARGO_*` does not, so it cannot forward `CARGO_REGISTRY_TOKEN` by
+/// accident.
+pub(crate) const SECRET_PATTERNS: &[&str] = &[
+    "AWS_*",
+    "AZURE_CLIENT_SECRET",
+    "GOOGLE_APPLICATION_CREDENTIALS",
+    "*_TOKEN",
+    "*_SECRET",
+    "*_SECRET_KEY",
+    "*_API_KEY",
+    "*_ACCESS_KEY",
+    "*_PRIVATE_KEY",
+    "*PASSWORD*",
+    "*PASSWD*",
+    "*_CREDENTIALS",
+];
+
+/// Whether `name` matches `pattern`, where `*` stands for any run of
+/// characters and `?` for any one.
+pub(crate) fn matches(pattern: &str, name: &str) -> bool {
+    let (pattern, name) = (pattern.as_bytes(), name.as_bytes());
+    let (mut p, mut n) = (0, 0);
+    // Where the last `*` was, and the name position it is matching up to.
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == b'?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((sp, sn)) => {
+                    p = sp + 1;
+                    n = sn + 1;
+                    star = Some((sp, sn + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// Whether `name` is a valid environment variable name: letters, digits and
+/// underscores, not starting with a digit.
+pub(crate) fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// The variables of one `--env-file`, read when the flag is parsed.
+#[derive(Debug, Clone)]
+pub(crate) struct EnvFile {
+    pub(crate) path: PathBuf,
+    /// `NAME=VALUE` assignments, in file order.
+    pub(crate) vars: Vec<String>,
+}
+
+/// Read a `.env`-style file: one `NAME=VALUE` per line, with blank lines
+/// and `#` comments ignored, an optional leading `export`, and one pair of
+/// matching quotes around the value removed.
+pub(crate) fn read_env_file(s: &str) -> Result<EnvFile, String> {
+    let path = PathBuf::from(crate::expand_path(s));
+    let text = std::fs::read_to_string(&path)
+        .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+    let mut vars = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (name, value) = line
+            .split_once('=')
+            .filter(|(name, _)| is_valid_name(name.trim()))
+            .ok_or_else(|| format!("{}:{}: expected NAME=VALUE", path.display(), i + 1))?;
+        let value = value.trim();
+        let value = ['"', '\'']
+            .iter()
+            .find_map(|&q| value.strip_prefix(q)?.strip_suffix(q))
+            .unwrap_or(value);
+        vars.push(format!("{}={}", name.trim(), value));
+    }
+    Ok(EnvFile { path, vars })
+}
+
+/// The variables to pass into the unit, as `config` decides.
+pub(crate) fn forwarded(config: &Config) -> Vec<(String, OsString)> {
+    let denied = |name: &str| config.env_deny.iter().any(|p| matches(p, name));
+    let mut env = Vec::new();
+    for (key, value) in std::env::vars_os() {
+        // systemd rejects names that are not shell identifiers, such as
+        // exported bash functions (`BASH_FUNC_name%%`).
+        let Some(key) = key.to_str().filter(|k| is_valid_name(k)) else {
+            continue;
+        };
+        let key = key.to_string();
+        // systemd-run sets this one itself.
+        if key == "DBUS_SESSION_BUS_ADDRESS" {
+            continue;
+        }
+        let captured = config.capture_env || (config.capture_path && key == "PATH");
+        let allowed = config.env_allow.iter().any(|p| matches(p, &key));
+        if !(captured || allowed) || denied(&key) {
+            continue;
+        }
+        let secret = SECRET_PATTERNS.iter().any(|p| matches(p, &key));
+        if secret && !config.env_allow.contains(&key) {
+            continue;
+        }
+        env.push((key, value));
+    }
+    for assignment in &config.setenv {
+        if let Some((key, value)) = assignment.split_once('=') {
+            env.retain(|(k, _)| k != key);
+            env.push((key.to_string(), value.into()));
+        }
+    }
+    env
+}
+
+/// Split `env` into the `NAME=VALUE` assignments systemd can carry and the
+/// command line to run. A value that is not UTF-8 cannot travel as an
+/// assignment, so when there is one the command runs under `/bin/sh`, which
+/// rebuilds each such value from octal escapes and then execs it.
+pub(crate) fn for_systemd(
+    env: &[(String, OsString)],
+    command: &[String],
+) -> (Vec<String>, Vec<String>) {
+    let mut assignments = Vec::new();
+    let mut script = String::new();
+    for (name, value) in env {
+        match value.to_str() {
+            Some(value) => assignments.push(format!("{}={}", name, value)),
+            None => script.push_str(&set_from_octal(name, value)),
+        }
+    }
+    if script.is_empty() {
+        return (assignments, command.to_vec());
+    }
+    script.push_str("exec \"$@\"");
+    let mut wrapped = vec![
+        "/bin/sh".to_string(),
+        "-c".to_string(),
+        script,
+        "sh".to_string(),
+    ];
+    wrapped.extend(command.iter().cloned());
+    (assignments, wrapped)
+}
+
+/// Shell code that exports `name` with exactly the bytes of `value`. The
+/// trailing `x` keeps command substitution from eating final newlines.
+fn set_from_octal(name: &str, value: &OsStr) -> String {
+    let octal: String = value
+        .as_bytes()
+        .iter()
+        .map(|b| format!("\\{:03o}", b))
+        .collect();
+    format!(
+        "{name}=$(printf '{octal}x'); {name}=${{{name}%x}}; export {name}; ",
+        name = name,
+        octal = octal
+    )
+}
@@ -1,24 +1,99 @@
-use anyhow::Result;
+use anyhow::{anyhow, bail, Context, Result};
 use atty::Stream;
 use clap::builder::BoolishValueParser;
 use clap::ArgAction;
-use clap::Parser;
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use nix::unistd::execvp;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::ffi::CString;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+mod dbus;
+mod environment;
+mod path_vars;
+mod project;
+mod secrets;
+mod size;
+mod trust;
 
 // ============ Profile Definitions ============
 
 /// A named bundle of resource limits and filesystem access tuned for a
 /// common workload (a Cargo build, a pytest run, etc.). A profile only
 /// supplies a baseline; any explicit CLI flag overrides the matching field.
-#[derive(Debug, Clone, Copy)]
+///
+/// Built-in profiles live in `profiles.toml` next to this file; users add
+/// their own in the same format (see `profile_files`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
 struct Profile {
-    name: &'static str,
-    description: &'static str,
-    memory_limit: Option<&'static str>,
-    cpu_quota: Option<&'static str>,
-    memory_swap_max: Option<&'static str>,
-    protect_home: &'static str,
+    name: String,
+    #[serde(default)]
+    description: String,
+    /// Name of a profile to start from. Only the fields this profile sets
+    /// override the parent's; paths are added to the parent's. A profile
+    /// that extends its own name builds on the definition it shadows.
+    extends: Option<String>,
+    #[serde(flatten)]
+    settings: Settings,
+}
+
+impl Profile {
+    /// Layer `self` over `base`: every limit `self` sets wins, the rest come
+    /// from `base`, and paths and environment patterns from both are kept
+    /// (base first, no duplicates).
+    /// This is the `extends` rule; `Config::resolve` applies repeated
+    /// `--profile` flags the same way, one layer at a time.
+    fn merged_over(&self, base: &Profile) -> Profile {
+        Profile {
+            name: self.name.clone(),
+            description: if self.description.is_empty() {
+                base.description.clone()
+            } else {
+                self.description.clone()
+            },
+            extends: base.extends.clone(),
+            settings: self.settings.merged_over(&base.settings),
+        }
+    }
+}
+
+/// The sandbox settings a profile and a project file can both set. Each
+/// layers over the configuration below it through `Config::apply_settings`.
+#[derive(Debug, Clone, Default, Deserialize)]
+struct Settings {
+    memory_limit: Option<String>,
+    cpu_quota: Option<String>,
+    memory_swap_max: Option<String>,
+    /// Process and thread ceiling (`TasksMax=`): a count, a percentage of
+    /// the system limit, or `infinity`.
+    tasks_max: Option<String>,
+    /// CPUs and NUMA nodes the command may run on, as lists such as `0-3,8`.
+    allowed_cpus: Option<String>,
+    allowed_memory_nodes: Option<String>,
+    /// Scheduling priority relative to other units and processes: a
+    /// `CPUWeight=` (1–10000, or `idle`), a nice level and an IO class.
+    cpu_weight: Option<String>,
+    nice: Option<i32>,
+    io_scheduling_class: Option<IoSchedulingClass>,
+    /// Read/write bandwidth ceilings, as for `--disk-read`/`--disk-write`.
+    disk_read: Option<String>,
+    disk_write: Option<String>,
+    /// Read/write operation ceilings, as for `--disk-read-iops`/
+    /// `--disk-write-iops`.
+    disk_read_iops: Option<String>,
+    disk_write_iops: Option<String>,
+    /// `IOWeight=`, 1 to 10000.
+    io_weight: Option<String>,
+    /// Default wall-clock limit, as for `--timeout`.
+    timeout: Option<String>,
+    /// systemd `ProtectHome` value. `None` leaves the home directory
+    /// unrestricted; every built-in profile sets it.
+    protect_home: Option<String>,
+    /// systemd `ProtectSystem` value, as for `--protect-system`.
+    protect_system: Option<String>,
     /// Network isolation opinion. `Some(true)` = `PrivateNetwork=yes`,
     /// `Some(false)` = `PrivateNetwork=no`, `None` = no opinion (the property
     /// is not emitted, leaving network available — systemd's default).
@@ -30,183 +105,874 @@ struct Profile {
     /// `--private-network`. The field exists so a future PR can opt individual
     /// profiles in once usage shows which are genuinely network-free.
     private_network: Option<bool>,
-    rw_paths: &'static [&'static str],
-    ro_paths: &'static [&'static str],
+    /// Which directory to bind for the project, as for `--project-root`.
+    project_root: Option<String>,
+    #[serde(default)]
+    rw_paths: Vec<String>,
+    #[serde(default)]
+    ro_paths: Vec<String>,
+    /// Variables the workload needs forwarded, as for `--env-allow`, and
+    /// ones it must never see, as for `--env-deny`.
+    #[serde(default)]
+    env_allow: Vec<String>,
+    #[serde(default)]
+    env_deny: Vec<String>,
+    /// Make secret-looking files in the project directory inaccessible, as
+    /// for `--mask-secrets`, and extra file name patterns to look for.
+    mask_secrets: Option<bool>,
+    #[serde(default)]
+    secret_patterns: Vec<String>,
+    /// Keep git hooks and configuration read-only, as for `--protect-git`.
+    protect_git: Option<bool>,
+}
+
+impl Settings {
+    /// `self` over `base`, by the rule described on `Profile::merged_over`.
+    fn merged_over(&self, base: &Settings) -> Settings {
+        let union = |base: &[String], ours: &[String]| {
+            let mut all = base.to_vec();
+            for p in ours {
+                if !all.contains(p) {
+                    all.push(p.clone());
+                }
+            }
+            all
+        };
+        Settings {
+            memory_limit: self
+                .memory_limit
+                .clone()
+                .or_else(|| base.memory_limit.clone()),
+            cpu_quota: self.cpu_quota.clone().or_else(|| base.cpu_quota.clone()),
+            memory_swap_max: self
+                .memory_swap_max
+                .clone()
+                .or_else(|| base.memory_swap_max.clone()),
+            tasks_max: self.tasks_max.clone().or_else(|| base.tasks_max.clone()),
+            allowed_cpus: self
+                .allowed_cpus
+                .clone()
+                .or_else(|| base.allowed_cpus.clone()),
+            allowed_memory_nodes: self
+                .allowed_memory_nodes
+                .clone()
+                .or_else(|| base.allowed_memory_nodes.clone()),
+            cpu_weight: self.cpu_weight.clone().or_else(|| base.cpu_weight.clone()),
+            nice: self.nice.or(base.nice),
+            io_scheduling_class: self.io_scheduling_class.or(base.io_scheduling_class),
+            disk_read: self.disk_read.clone().or_else(|| base.disk_read.clone()),
+            disk_write: self.disk_write.clone().or_else(|| base.disk_write.clone()),
+            disk_read_iops: self
+                .disk_read_iops
+                .clone()
+                .or_else(|| base.disk_read_iops.clone()),
+            disk_write_iops: self
+                .disk_write_iops
+                .clone()
+                .or_else(|| base.disk_write_iops.clone()),
+            io_weight: self.io_weight.clone().or_else(|| base.io_weight.clone()),
+            timeout: self.timeout.clone().or_else(|| base.timeout.clone()),
+            protect_home: self
+                .protect_home
+                .clone()
+                .or_else(|| base.protect_home.clone()),
+            protect_system: self
+                .protect_system
+                .clone()
+                .or_else(|| base.protect_system.clone()),
+            private_network: self.private_network.or(base.private_network),
+            project_root: self
+                .project_root
+                .clone()
+                .or_else(|| base.project_root.clone()),
+            rw_paths: union(&base.rw_paths, &self.rw_paths),
+            ro_paths: union(&base.ro_paths, &self.ro_paths),
+            env_allow: union(&base.env_allow, &self.env_allow),
+            env_deny: union(&base.env_deny, &self.env_deny),
+            mask_secrets: self.mask_secrets.or(base.mask_secrets),
+            secret_patterns: union(&base.secret_patterns, &self.secret_patterns),
+            protect_git: self.protect_git.or(base.protect_git),
+        }
+    }
+
+    /// Run every setting that is present through the value parser of the
+    /// matching command-line flag.
+    fn check(&self) -> Result<(), String> {
+        check_settings(
+            &[
+                ("timeout", &self.timeout, parse_timespan),
+                ("memory_limit", &self.memory_limit, check_size),
+                ("memory_swap_max", &self.memory_swap_max, check_size),
+                ("disk_read", &self.disk_read, check_rate),
+                ("disk_write", &self.disk_write, check_rate),
+                ("disk_read_iops", &self.disk_read_iops, check_iops),
+                ("disk_write_iops", &self.disk_write_iops, check_iops),
+                ("io_weight", &self.io_weight, check_io_weight),
+                ("cpu_quota", &self.cpu_quota, check_cpu_quota),
+                ("tasks_max", &self.tasks_max, check_tasks_max),
+                ("allowed_cpus", &self.allowed_cpus, check_cpu_set),
+                (
+                    "allowed_memory_nodes",
+                    &self.allowed_memory_nodes,
+                    check_cpu_set,
+                ),
+                ("cpu_weight", &self.cpu_weight, check_cpu_weight),
+                ("project_root", &self.project_root, check_project_root),
+                ("protect_home", &self.protect_home, check_protect_home),
+                ("protect_system", &self.protect_system, check_protect_system),
+            ],
+            self.nice,
+        )?;
+        check_env_patterns(&self.env_allow, &self.env_deny)?;
+        for pattern in &self.secret_patterns {
+            check_secret_pattern(pattern).map_err(|e| format!("bad secret_patterns: {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+/// The on-disk shape of a profiles file: a list of `[[profile]]` tables.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProfileFile {
+    #[serde(default)]
+    profile: Vec<Profile>,
 }
 
 const DEFAULT_CPU_QUOTA_PERIOD: &str = "100ms";
 
-const PROFILES: &[Profile] = &[
-    Profile {
-        name: "cargo",
-        description: "Rust/Cargo builds and tests",
-        memory_limit: Some("2G"),
-        cpu_quota: Some("300%"),
-        memory_swap_max: Some("0"),
-        protect_home: "tmpfs",
-        private_network: None,
-        rw_paths: &["$HOME/.cargo"],
-        ro_paths: &["$HOME/.rustup"],
-    },
-    Profile {
-        name: "npm",
-        description: "Node.js npm/yarn/pnpm build and test",
-        memory_limit: Some("1G"),
-        cpu_quota: Some("200%"),
-        memory_swap_max: Some("0"),
-        protect_home: "tmpfs",
-        private_network: None,
-        rw_paths: &["$HOME/.npm", "$HOME/.cache/yarn", "$HOME/.local/share/pnpm"],
-        ro_paths: &["$HOME/.local/share/fnm", "/run/user/$UID"],
-    },
-    Profile {
-        name: "pytest",
-        description: "Python pytest (single or parallel mode)",
-        memory_limit: Some("512M"),
-        cpu_quota: Some("200%"),
-        memory_swap_max: Some("0"),
-        protect_home: "tmpfs",
-        private_network: None,
-        rw_paths: &[],
-        ro_paths: &["$HOME/.local/lib"],
-    },
-    Profile {
-        name: "python",
-        description: "General Python script execution",
-        memory_limit: Some("512M"),
-        cpu_quota: Some("100%"),
-        memory_swap_max: Some("0"),
-        protect_home: "tmpfs",
-        private_network: None,
-        rw_paths: &[],
-        ro_paths: &["$HOME/.local/lib"],
-    },
-    Profile {
-        name: "uv",
-        description: "Python uv dependency management",
-        memory_limit: Some("256M"),
-        cpu_quota: Some("200%"),
-        memory_swap_max: Some("0"),
-        protect_home: "tmpfs",
-        private_network: None,
-        rw_paths: &["$HOME/.cache/uv", "$HOME/.local/share/uv"],
-        ro_paths: &[],
-    },
-    Profile {
-        name: "go",
-        description: "Go builds and tests",
-        memory_limit: Some("512M"),
-        cpu_quota: Some("300%"),
-        memory_swap_max: None,
-        protect_home: "tmpfs",
-        private_network: None,
-        rw_paths: &["$HOME/go", "$HOME/.cache/go-build"],
-        ro_paths: &[],
-    },
-    Profile {
-        name: "make",
-        description: "C/C++ make/cmake builds",
-        memory_limit: Some("2G"),
-        cpu_quota: Some("300%"),
-        memory_swap_max: Some("0"),
-        protect_home: "tmpfs",
-        private_network: None,
-        rw_paths: &[],
-        ro_paths: &[],
-    },
-    Profile {
-        name: "coding-agent",
-        description: "AI coding agent (claude, codex, gemini, pi, etc.)",
-        memory_limit: Some("4G"),
-        cpu_quota: Some("200%"),
-        memory_swap_max: Some("0"),
-        protect_home: "tmpfs",
-        private_network: None,
-        rw_paths: &[],
-        ro_paths: &["$HOME/.gitconfig", "$HOME/.ssh"],
-    },
-    Profile {
-        name: "shell",
-        description: "Interactive shell/terminal session (read-only home)",
-        memory_limit: Some("4G"),
-        cpu_quota: None,
-        memory_swap_max: Some("0"),
-        protect_home: "read-only",
-        private_network: None,
-        rw_paths: &["$HOME/.local/share", "$HOME/.cache", "$HOME/.local/bin"],
-        ro_paths: &[],
-    },
-];
+const BUILTIN_PROFILES: &str = include_str!("profiles.toml");
+
+/// Profile files read on top of the built-ins, lowest precedence first: the
+/// system-wide file, then the user's own.
+fn profile_files() -> Vec<PathBuf> {
+    let mut files = vec![PathBuf::from("/etc/playpen/profiles.toml")];
+    if let Some(dir) = config_dir() {
+        files.push(dir.join("playpen/profiles.toml"));
+    }
+    files
+}
+
+/// The user's configuration directory: `$XDG_CONFIG_HOME`, falling back to
+/// `$HOME/.config`.
+fn config_dir() -> Option<PathBuf> {
+    path_vars::lookup("XDG_CONFIG_HOME").map(PathBuf::from)
+}
+
+/// Load the built-in profiles and then every profile file that exists. A
+/// profile whose name is already defined replaces the earlier definition in
+/// place, so user profiles can shadow built-ins while listing order stays
+/// stable. `extends` chains are flattened before returning.
+fn load_profiles() -> Result<Vec<Profile>> {
+    let builtin: ProfileFile =
+        toml::from_str(BUILTIN_PROFILES).expect("built-in profiles.toml is valid");
+    let mut profiles = builtin.profile;
+
+    for path in profile_files() {
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+        };
+        let file: ProfileFile =
+            toml::from_str(&text).with_context(|| format!("failed to parse {}", path.display()))?;
+        for p in file.profile {
+            p.settings
+                .check()
+                .map_err(|e| anyhow!("profile {} in {}: {}", p.name, path.display(), e))?;
+            match profiles.iter_mut().find(|existing| existing.name == p.name) {
+                // `extends` naming itself means "the definition I replace".
+                Some(existing) if p.extends.as_deref() == Some(p.name.as_str()) => {
+                    *existing = p.merged_over(existing)
+                }
+                Some(existing) => *existing = p,
+                None => profiles.push(p),
+            }
+        }
+    }
+
+    profiles
+        .iter()
+        .map(|p| flatten_profile(&profiles, p, &mut Vec::new()))
+        .collect()
+}
+
+/// Resolve `p`'s `extends` chain into a single self-contained profile.
+/// `chain` holds the names already visited, to report cycles.
+fn flatten_profile(all: &[Profile], p: &Profile, chain: &mut Vec<String>) -> Result<Profile> {
+    let Some(parent_name) = &p.extends else {
+        return Ok(p.clone());
+    };
+    if chain.contains(&p.name) {
+        chain.push(p.name.clone());
+        bail!("profile inheritance cycle: {}", chain.join(" -> "));
+    }
+    chain.push(p.name.clone());
+    let parent = all.iter().find(|q| &q.name == parent_name).ok_or_else(|| {
+        anyhow!(
+            "profile '{}' extends unknown profile '{}'",
+            p.name,
+            parent_name
+        )
+    })?;
+    let base = flatten_profile(all, parent, chain)?;
+    Ok(p.merged_over(&base))
+}
 
 /// Look up a profile by name, printing the list of valid profiles and
 /// exiting if the name is not recognized.
-fn lookup_profile(name: &str) -> &'static Profile {
-    PROFILES.iter().find(|p| p.name == name).unwrap_or_else(|| {
+fn lookup_profile<'a>(profiles: &'a [Profile], name: &str) -> &'a Profile {
+    profiles.iter().find(|p| p.name == name).unwrap_or_else(|| {
         eprintln!("error: unknown profile '{}'\n", name);
         eprintln!("Valid profiles:");
-        for p in PROFILES {
+        for p in profiles {
             eprintln!("  {:12} - {}", p.name, p.description);
         }
         std::process::exit(1);
     })
 }
 
-/// Expand `$HOME`, `$UID` and similar variables in a profile path.
+/// Expand a leading `~` and variables such as `$HOME` in a path, with the
+/// built-in ones from `path_vars` (`$UID`, `$PROJECT_ROOT`, ...) available
+/// even when the environment does not set them. An unknown variable is
+/// left as written.
 fn expand_path(path: &str) -> String {
-    shellexpand::env(path)
-        .unwrap_or_else(|_| path.into())
+    shellexpand::full_with_context_no_errors(path, || path_vars::lookup("HOME"), path_vars::lookup)
         .into_owned()
 }
 
-/// Add a profile path to `list`, but only if it currently exists. systemd-run
-/// refuses to start if asked to bind-mount a missing path, so a profile that
-/// names, say, `$HOME/.cargo` on a machine without Cargo simply skips it.
-fn push_if_exists(list: &mut Vec<String>, raw: &str) {
-    let expanded = expand_path(raw);
-    if std::path::Path::new(&expanded).exists() {
-        list.push(expanded);
+/// `path` made absolute against the working directory, with the directory
+/// it is in resolved to its real location. The last component is kept as
+/// given, so a bound symlink stays a symlink rather than becoming its
+/// target.
+fn resolve_path(path: &Path) -> PathBuf {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    match (path.parent().map(std::fs::canonicalize), path.file_name()) {
+        (Some(Ok(dir)), Some(name)) => dir.join(name),
+        // `/`, or a path ending in `..`.
+        _ => std::fs::canonicalize(&path).unwrap_or(path),
+    }
+}
+
+// ============ Project File ============
+
+/// Name of the per-project policy file, discovered by walking up from the
+/// working directory.
+const PROJECT_FILE_NAME: &str = ".playpen.toml";
+
+/// A checked-in sandbox policy for one repository: the `Settings` a profile
+/// can set, plus a few that only make sense per project. Every field is
+/// optional and an unset field leaves the profile's value alone.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProjectFile {
+    /// Where the file was found; not part of the file itself.
+    #[serde(skip)]
+    path: PathBuf,
+    /// Profile to use when `--profile` is not given on the command line.
+    profile: Option<String>,
+    #[serde(flatten)]
+    settings: Settings,
+    #[serde(default)]
+    inaccessible_paths: Vec<String>,
+    #[serde(default)]
+    ip_allow: Vec<String>,
+    #[serde(default)]
+    ip_deny: Vec<String>,
+    #[serde(default)]
+    socket_bind_allow: Vec<String>,
+    #[serde(default)]
+    socket_bind_deny: Vec<String>,
+}
+
+impl ProjectFile {
+    /// The file with its paths expanded and then resolved against the file's
+    /// own directory, so `rw_paths = ["target"]` means the same thing from
+    /// any subdirectory. `Config::resolve` does this once `$PROJECT_ROOT` is
+    /// known.
+    fn with_resolved_paths(&self) -> ProjectFile {
+        let mut file = self.clone();
+        let root = self.path.parent().unwrap_or(Path::new("/"));
+        for list in [
+            &mut file.settings.rw_paths,
+            &mut file.settings.ro_paths,
+            &mut file.inaccessible_paths,
+        ] {
+            for entry in list.iter_mut() {
+                *entry = root.join(expand_path(entry)).display().to_string();
+            }
+        }
+        file
+    }
+}
+
+/// Find the nearest `.playpen.toml` in `start` or any of its ancestors.
+fn find_project_file(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_FILE_NAME))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Load the project file governing the working directory, if any. One that
+/// has not been approved as it is now with `playpen trust` is ignored, with
+/// a warning.
+fn load_project_file() -> Result<Option<ProjectFile>> {
+    let cwd = std::env::current_dir().context("failed to read the current directory")?;
+    let Some(path) = find_project_file(&cwd) else {
+        return Ok(None);
+    };
+    let text = std::fs::read_to_string(&path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let trusted = match config_dir() {
+        Some(dir) => trust::is_trusted(&trust::store_path(&dir), &path, &text)?,
+        None => false,
+    };
+    if !trusted {
+        eprintln!(
+            "playpen: ignoring {}, which is not trusted; review it and run `playpen trust` to apply it",
+            path.display()
+        );
+        return Ok(None);
     }
+    parse_project_file(path, &text).map(Some)
+}
+
+/// Parse and check the project file at `path`, whose contents are `text`.
+fn parse_project_file(path: PathBuf, text: &str) -> Result<ProjectFile> {
+    let mut file: ProjectFile =
+        toml::from_str(text).with_context(|| format!("failed to parse {}", path.display()))?;
+    let root = path.parent().unwrap_or(Path::new("/"));
+    if let Some(dir) = file
+        .settings
+        .project_root
+        .as_mut()
+        .filter(|v| !project::MODES.contains(&v.as_str()))
+    {
+        *dir = root.join(expand_path(dir)).display().to_string();
+    }
+
+    file.settings
+        .check()
+        .map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+
+    file.path = path;
+
+    Ok(file)
 }
 
 // ============ CLI ============
 
-const PROFILE_HELP: &str = "Use a predefined resource and filesystem profile. Valid profiles: cargo, npm, pytest, python, uv, go, make, coding-agent, shell";
+const PROFILE_HELP: &str = "Use a predefined resource and filesystem profile; repeat to combine several (later limits win, paths add up). Valid profiles: cargo, npm, pytest, python, uv, go, make, coding-agent, shell, plus any defined in ~/.config/playpen/profiles.toml";
 
+/// Top-level command line. With no subcommand, the options and trailing
+/// command form a `Run`, so `playpen [OPTIONS] cmd args...` keeps working;
+/// `args_conflicts_with_subcommands` stops a run option from being mistaken
+/// for a subcommand's. To run a program literally named like a subcommand,
+/// put it after `--`. clap's implicit `help` subcommand is disabled so that
+/// `playpen help` still runs a program called `help`.
 #[derive(Parser)]
-#[command(version, about, long_about = None)]
+#[command(
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    disable_help_subcommand = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    run: Run,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Inspect the built-in and user-defined profiles
+    #[command(subcommand)]
+    Profiles(ProfilesCommand),
+    /// Run a command once without limits and suggest a profile from what it used
+    Suggest(SuggestArgs),
+    /// Approve a .playpen.toml as it is now, so playpen applies it
+    Trust {
+        /// The file to approve (default: the one governing the current directory)
+        path: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProfilesCommand {
+    /// List every available profile
+    List,
+    /// Show a profile's resolved limits and paths
+    Show { name: String },
+    /// Show the settings that differ between two profiles
+    Diff { a: String, b: String },
+}
+
+#[derive(Args)]
+struct SuggestArgs {
+    #[arg(
+        long,
+        help = "Name of the suggested profile (default: the command's name)"
+    )]
+    name: Option<String>,
+
+    #[arg(
+        long,
+        default_value_t = 1.5,
+        help = "Multiply each measured peak by this factor before rounding"
+    )]
+    headroom: f64,
+
+    #[arg(required = true)]
+    command_and_args: Vec<String>,
+}
+
+/// Check a systemd time span (`90s`, `30m`, `1h30m`) and keep it as given,
+/// to be passed on to systemd verbatim.
+fn parse_timespan(s: &str) -> Result<String, String> {
+    dbus::parse_usec(s).map_err(|e| e.to_string())?;
+    Ok(s.to_string())
+}
+
+/// Resolve the `--{flag}` percentage `value` against the memory limit
+/// `max`: `90%` of `2G` becomes `1887436K`, and `90%` of `50%` becomes
+/// `45%` (of physical memory). Without a finite limit there is nothing to
+/// take a share of, which is an error. Anything else is returned unchanged.
+fn share_of_limit(flag: &str, value: &str, max: Option<&str>) -> Result<String> {
+    let Some(pct) = value.strip_suffix('%').and_then(|p| p.parse::<f64>().ok()) else {
+        return Ok(value.to_string());
+    };
+    let Some(max) = max.filter(|&m| finite_limit(m)) else {
+        bail!(
+            "--{} {} is a share of the memory limit; set one with -m/--memory-limit",
+            flag,
+            value
+        );
+    };
+    if let Some(max_pct) = max.strip_suffix('%').and_then(|p| p.parse::<f64>().ok()) {
+        return Ok(format!("{}%", (max_pct * pct).round() / 100.0));
+    }
+    match size::parse_bytes(max) {
+        Ok(bytes) => Ok(size::format_size((bytes as f64 * pct / 100.0) as u64)),
+        Err(_) => Ok(value.to_string()),
+    }
+}
+
+/// Whether the memory limit `max` caps anything.
+fn finite_limit(max: &str) -> bool {
+    max != "infinity"
+}
+
+/// A check for a setting read from a profile or project file: the value
+/// parser of the matching command-line flag.
+type Check = fn(&str) -> Result<String, String>;
+
+/// Access to one of the path lists of a `Config`, for code that handles
+/// several of them alike.
+type ListField = fn(&mut Config) -> &mut Vec<String>;
+
+/// Run each setting that is present through its check, and the nice level
+/// through its range, reporting the first failure as `bad <field>: ...`.
+fn check_settings(
+    settings: &[(&str, &Option<String>, Check)],
+    nice: Option<i32>,
+) -> Result<(), String> {
+    for (field, value, check) in settings {
+        if let Some(v) = value {
+            check(v).map_err(|e| format!("bad {}: {}", field, e))?;
+        }
+    }
+    if let Some(n) = nice {
+        if !NICE_RANGE.contains(&n) {
+            return Err(format!("bad nice: {} is not in -20..=19", n));
+        }
+    }
+    Ok(())
+}
+
+/// Nice levels the kernel accepts.
+const NICE_RANGE: std::ops::RangeInclusive<i32> = -20..=19;
+
+/// Check a memory value: a size (`512M`, `2G`), a percentage, a share of
+/// physical memory (`25%ram`) or `infinity`.
+fn check_size(s: &str) -> Result<String, String> {
+    size::Size::parse(s).map_err(|e| e.to_string())?;
+    Ok(s.to_string())
+}
+
+/// Check a disk bandwidth such as `50M` (bytes per second).
+fn check_rate(s: &str) -> Result<String, String> {
+    size::parse_rate(s).map_err(|e| e.to_string())?;
+    Ok(s.to_string())
+}
+
+/// Check a disk operation rate such as `1000` (operations per second) or
+/// `infinity`.
+fn check_iops(s: &str) -> Result<String, String> {
+    match s.parse::<u64>() {
+        _ if s == "infinity" => Ok(s.to_string()),
+        Ok(n) if n > 0 => Ok(s.to_string()),
+        _ => Err(format!(
+            "invalid IOPS limit: {} (expected a positive count or infinity)",
+            s
+        )),
+    }
+}
+
+/// Check an `IOWeight=` value: 1 to 10000.
+fn check_io_weight(s: &str) -> Result<String, String> {
+    match s.parse::<u64>() {
+        Ok(1..=10000) => Ok(s.to_string()),
+        _ => Err(format!("invalid IO weight: {} (expected 1-10000)", s)),
+    }
+}
+
+/// Check a disk limit given as `VALUE`, or as `PATH=VALUE` to limit only
+/// the device behind `PATH`. The path must exist and is returned absolute,
+/// since systemd resolves it from its own working directory.
+fn check_device_limit(s: &str, check: Check) -> Result<String, String> {
+    let Some((path, value)) = s.rsplit_once('=') else {
+        return check(s);
+    };
+    check(value)?;
+    let path = std::path::absolute(expand_path(path)).map_err(|e| e.to_string())?;
+    if !path.exists() {
+        return Err(format!("no such path: {}", path.display()));
+    }
+    Ok(format!("{}={}", path.display(), value))
+}
+
+/// Check a `--rw`, `--ro` or `--inaccessible` path: expand it, resolve it
+/// with `resolve_path` and make sure it exists. A leading `-` makes the path
+/// optional, as in systemd: it is kept, and systemd skips the path if it is
+/// missing.
+fn check_path(s: &str) -> Result<String, String> {
+    let (optional, path) = match s.strip_prefix('-') {
+        Some(path) => ("-", path),
+        None => ("", s),
+    };
+    let path = resolve_path(Path::new(&expand_path(path)));
+    if optional.is_empty() && path.symlink_metadata().is_err() {
+        return Err(format!(
+            "no such path: {} (prefix it with - to skip it when missing)",
+            path.display()
+        ));
+    }
+    Ok(format!("{}{}", optional, path.display()))
+}
+
+/// Catch a forgotten `--rw`, `--ro` or `--inaccessible` path: the flags take
+/// values starting with `-`, so `--rw --dry-run` would take `--dry-run` as
+/// the path. The path itself is checked by `Config::resolve`.
+fn check_path_flag(s: &str) -> Result<String, String> {
+    if s.starts_with("--") {
+        return Err(format!("expected a path, found {}", s));
+    }
+    Ok(s.to_string())
+}
+
+/// `check_path` for a bind: `[-]SOURCE[:DEST[:OPTIONS]]`, with the source
+/// checked and the destination expanded.
+fn check_bind_path(s: &str) -> Result<String, String> {
+    match s.split_once(':') {
+        Some((source, rest)) => Ok(format!("{}:{}", check_path(source)?, expand_path(rest))),
+        None => check_path(s),
+    }
+}
+
+/// Check a `--project-root` value: a mode from `project::MODES`, or an
+/// existing directory, returned expanded and absolute.
+fn check_project_root(s: &str) -> Result<String, String> {
+    if project::MODES.contains(&s) {
+        return Ok(s.to_string());
+    }
+    let path = resolve_path(Path::new(&expand_path(s)));
+    if !path.is_dir() {
+        return Err(format!(
+            "not a directory: {} (expected auto, git, cwd or a directory)",
+            path.display()
+        ));
+    }
+    Ok(path.display().to_string())
+}
+
+/// `check_device_limit` for a bandwidth (`50M`, `/data=20M`).
+fn check_device_rate(s: &str) -> Result<String, String> {
+    check_device_limit(s, check_rate)
+}
+
+/// `check_device_limit` for an operation rate (`1000`, `/data=500`).
+fn check_device_iops(s: &str) -> Result<String, String> {
+    check_device_limit(s, check_iops)
+}
+
+/// Check an `--env-allow`/`--env-deny` pattern: a variable name in which
+/// `*` and `?` may stand for any run of characters and any one character.
+fn check_env_pattern(s: &str) -> Result<String, String> {
+    if environment::is_valid_name(&s.replace(['*', '?'], "_")) {
+        Ok(s.to_string())
+    } else {
+        Err(format!(
+            "invalid variable pattern: {} (expected a name such as CARGO_* or RUSTFLAGS)",
+            s
+        ))
+    }
+}
+
+/// Run `check_env_pattern` over a profile's or project file's `env_allow`
+/// and `env_deny` lists.
+fn check_env_patterns(allow: &[String], deny: &[String]) -> Result<(), String> {
+    for (field, list) in [("env_allow", allow), ("env_deny", deny)] {
+        for pattern in list {
+            check_env_pattern(pattern).map_err(|e| format!("bad {}: {}", field, e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Check a `--secret-pattern`: a file name in which `*` and `?` may stand
+/// for any run of characters and any one character. A pattern of nothing
+/// but wildcards would mask every file in the project, so it is refused.
+fn check_secret_pattern(s: &str) -> Result<String, String> {
+    if s.is_empty() {
+        Err("invalid secret pattern: empty (expected a file name such as *.secret)".to_string())
+    } else if s.contains('/') {
+        Err(format!(
+            "invalid secret pattern: {} (expected a file name such as *.secret, not a path)",
+            s
+        ))
+    } else if s.chars().all(|c| c == '*' || c == '?') {
+        Err(format!(
+            "invalid secret pattern: '{}' (a pattern of only wildcards would mask the whole project)",
+            s
+        ))
+    } else {
+        Ok(s.to_string())
+    }
+}
+
+/// Check a `--setenv` assignment, `NAME=VALUE`.
+fn check_setenv(s: &str) -> Result<String, String> {
+    match s.split_once('=') {
+        Some((name, _)) if environment::is_valid_name(name) => Ok(s.to_string()),
+        _ => Err(format!("invalid assignment: {} (expected NAME=VALUE)", s)),
+    }
+}
+
+/// Check a CPU limit: a `CPUQuota=` percentage (`300%`), a number of cores
+/// (`3cores`, `1core`, `0.5cores`) or a share of the machine (`50%host`).
+fn check_cpu_quota(s: &str) -> Result<String, String> {
+    let number = s
+        .strip_suffix("%host")
+        .or_else(|| s.strip_suffix("cores"))
+        .or_else(|| s.strip_suffix("core"))
+        .or_else(|| s.strip_suffix('%'));
+    match number.map(str::parse::<f64>) {
+        Some(Ok(n)) if n > 0.0 && n.is_finite() => Ok(s.to_string()),
+        _ => Err(format!(
+            "invalid CPU limit: {} (expected e.g. 300%, 3cores or 50%host)",
+            s
+        )),
+    }
+}
+
+/// Turn a CPU limit in cores or as a share of the machine into the
+/// `CPUQuota=` percentage systemd takes, given the number of CPUs: on an
+/// 8-CPU host, `3cores` is `300%` and `50%host` is `400%`. A plain
+/// percentage is returned unchanged.
+fn cpu_quota_percent(value: &str, host_cpus: usize) -> String {
+    let percent = if let Some(share) = value.strip_suffix("%host") {
+        share.parse::<f64>().ok().map(|p| p * host_cpus as f64)
+    } else if let Some(cores) = value
+        .strip_suffix("cores")
+        .or_else(|| value.strip_suffix("core"))
+    {
+        cores.parse::<f64>().ok().map(|n| n * 100.0)
+    } else {
+        None
+    };
+    match percent {
+        Some(p) => format!("{}%", p.round().max(1.0)),
+        None => value.to_string(),
+    }
+}
+
+/// Check a CPU or NUMA node list such as `0-3,8`.
+fn check_cpu_set(s: &str) -> Result<String, String> {
+    dbus::parse_cpu_set(s).map_err(|e| e.to_string())?;
+    Ok(s.to_string())
+}
+
+/// Check a `CPUWeight=` value: 1 to 10000, or `idle`.
+fn check_cpu_weight(s: &str) -> Result<String, String> {
+    match s.parse::<u64>() {
+        _ if s == "idle" => Ok(s.to_string()),
+        Ok(1..=10000) => Ok(s.to_string()),
+        _ => Err(format!(
+            "invalid CPU weight: {} (expected 1-10000 or idle)",
+            s
+        )),
+    }
+}
+
+/// Check a `TasksMax=` value: a positive count, a percentage of the system
+/// limit, or `infinity`.
+fn check_tasks_max(s: &str) -> Result<String, String> {
+    let valid = match s.strip_suffix('%') {
+        _ if s == "infinity" => true,
+        Some(pct) => pct.parse::<f64>().is_ok_and(|p| p > 0.0 && p <= 100.0),
+        None => s.parse::<u64>().is_ok_and(|n| n > 0),
+    };
+    if valid {
+        Ok(s.to_string())
+    } else {
+        Err(format!(
+            "invalid task limit: {} (expected a count, a percentage or infinity)",
+            s
+        ))
+    }
+}
+
+/// Parse a systemd time span into a `Duration` for playpen's own use.
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    dbus::parse_usec(s)
+        .map(Duration::from_micros)
+        .map_err(|e| e.to_string())
+}
+
+/// Output format for `--dry-run`.
+#[derive(Clone, Copy, ValueEnum)]
+enum DryRunFormat {
+    /// The shell-quoted `systemd-run` command line.
+    Text,
+    /// The resolved `Config`, final argv and value sources, as JSON.
+    Json,
+}
+
+/// Output format for `--stats`.
+#[derive(Clone, Copy, ValueEnum)]
+enum StatsFormat {
+    /// One `playpen:` line per counter on stderr.
+    Text,
+    /// A single JSON object.
+    Json,
+}
+
+/// IO scheduling class (`IOSchedulingClass=`). `idle` only gets disk time
+/// when nothing else wants it.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+enum IoSchedulingClass {
+    Realtime,
+    BestEffort,
+    Idle,
+}
+
+impl std::fmt::Display for IoSchedulingClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = self.to_possible_value().expect("no skipped variants");
+        f.write_str(name.get_name())
+    }
+}
+
+/// What happens as the unit nears its memory limit.
+#[derive(Clone, Copy, ValueEnum)]
+enum MemoryPolicy {
+    /// Kill the command at the limit (`MemoryMax=` only).
+    Hard,
+    /// Throttle and reclaim from 90% of the limit (`MemoryHigh=`) before
+    /// killing at the limit.
+    Soft,
+    /// As soft, and let the command swap up to the limit again rather than
+    /// be killed.
+    Swap,
+}
+
+/// How the sandboxed command is launched.
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Backend {
+    /// Exec `systemd-run`, which creates the transient unit.
+    SystemdRun,
+    /// Create the transient unit over D-Bus and wait for it in-process.
+    Dbus,
+}
+
+#[derive(Args)]
 struct Run {
-    #[clap(short, long)]
+    #[clap(
+        short,
+        long,
+        value_parser = check_size,
+        help = "Limit memory (MemoryMax): a size such as 2G or 512M, a percentage of RAM such as 25%ram, or infinity"
+    )]
     memory_limit: Option<String>,
 
-    #[clap(short, long)]
+    #[clap(
+        short,
+        long,
+        value_parser = check_cpu_quota,
+        help = "Limit CPU time (CPUQuota): 300%, 3cores, or 50%host for a share of this machine"
+    )]
     cpu_limit: Option<String>,
 
     // Disk I/O bandwidth limits. `--disk-limit` caps the read and write
     // rates together; `--disk-read`/`--disk-write` cap a single direction and
     // override `--disk-limit` for that direction. Values are byte/sec rates
-    // for the block device backing the working directory, using systemd's
-    // K/M/G/T suffixes (e.g. 50M, 500K).
+    // using systemd's K/M/G/T suffixes (e.g. 50M, 500K). A plain value
+    // applies to every device behind the working directory and the `--rw`
+    // paths; `PATH=VALUE` limits only the device behind PATH. Each flag may
+    // be repeated.
     #[clap(
         short,
         long,
-        value_name = "VALUE",
-        help = "Limit disk I/O bandwidth, read and write, for the working directory's device (e.g. 50M, 500K)"
+        value_name = "[PATH=]VALUE",
+        value_parser = check_device_rate,
+        help = "Limit disk I/O bandwidth, read and write (e.g. 50M, or /data=20M for one path's device)"
     )]
-    disk_limit: Option<String>,
+    disk_limit: Vec<String>,
 
     #[clap(
         long,
-        value_name = "VALUE",
+        value_name = "[PATH=]VALUE",
+        value_parser = check_device_rate,
         help = "Limit disk read bandwidth only; overrides --disk-limit for reads"
     )]
-    disk_read: Option<String>,
+    disk_read: Vec<String>,
 
     #[clap(
         long,
-        value_name = "VALUE",
+        value_name = "[PATH=]VALUE",
+        value_parser = check_device_rate,
         help = "Limit disk write bandwidth only; overrides --disk-limit for writes"
     )]
-    disk_write: Option<String>,
+    disk_write: Vec<String>,
+
+    #[clap(
+        long,
+        value_name = "[PATH=]N",
+        value_parser = check_device_iops,
+        help = "Limit disk read operations per second (IOReadIOPSMax)"
+    )]
+    disk_read_iops: Vec<String>,
+
+    #[clap(
+        long,
+        value_name = "[PATH=]N",
+        value_parser = check_device_iops,
+        help = "Limit disk write operations per second (IOWriteIOPSMax)"
+    )]
+    disk_write_iops: Vec<String>,
+
+    #[clap(
+        long,
+        value_name = "WEIGHT",
+        value_parser = check_io_weight,
+        help = "Disk I/O weight relative to other units, 1-10000 (IOWeight; default 100)"
+    )]
+    io_weight: Option<String>,
 
     #[clap(short, long, default_value = "false")]
     quiet: bool,
@@ -217,32 +983,233 @@ struct Run {
     #[arg(long, action = ArgAction::Set, value_parser = BoolishValueParser::new(), default_value = "true")]
     capture_path: bool,
 
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        value_parser = check_env_pattern,
+        help = "Forward variables matching PATTERN, e.g. 'CARGO_*' (can be repeated)"
+    )]
+    env_allow: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        value_parser = check_env_pattern,
+        help = "Never forward variables matching PATTERN, e.g. 'AWS_*' (can be repeated)"
+    )]
+    env_deny: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "NAME=VALUE",
+        value_parser = check_setenv,
+        help = "Set a variable for the command (can be repeated)"
+    )]
+    setenv: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        value_parser = environment::read_env_file,
+        help = "Set the variables in a NAME=VALUE file for the command (can be repeated)"
+    )]
+    env_file: Vec<environment::EnvFile>,
+
     #[clap(long, value_name = "NAME", help = PROFILE_HELP)]
-    profile: Option<String>,
+    profile: Vec<String>,
 
     #[clap(
         long,
         value_name = "VALUE",
+        value_parser = check_size,
         help = "Set MemorySwapMax limit (e.g., 0, 1G)"
     )]
     memory_swap_max: Option<String>,
 
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = check_tasks_max,
+        help = "Limit the number of processes and threads (TasksMax), e.g. 512, 10%, infinity"
+    )]
+    tasks_max: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "LIST",
+        value_parser = check_cpu_set,
+        help = "Run only on these CPUs (AllowedCPUs), e.g. 0-3,8"
+    )]
+    allowed_cpus: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "LIST",
+        value_parser = check_cpu_set,
+        help = "Allocate memory only from these NUMA nodes (AllowedMemoryNodes), e.g. 0"
+    )]
+    allowed_memory_nodes: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "WEIGHT",
+        value_parser = check_cpu_weight,
+        help = "CPU share relative to other units (CPUWeight), 1-10000 or idle; the default is 100"
+    )]
+    cpu_weight: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        allow_negative_numbers = true,
+        value_parser = clap::value_parser!(i32).range(-20..=19),
+        help = "Nice level of the command, -20 to 19; negative levels need privileges"
+    )]
+    nice: Option<i32>,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "CLASS",
+        help = "IO scheduling class (IOSchedulingClass)"
+    )]
+    io_scheduling_class: Option<IoSchedulingClass>,
+
+    #[arg(
+        long,
+        value_name = "VALUE",
+        value_parser = check_size,
+        help = "Throttle and reclaim memory above this before the hard limit is reached (MemoryHigh); a percentage is of the memory limit (e.g. 1500M, 90%)"
+    )]
+    memory_high: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "VALUE",
+        value_parser = check_size,
+        help = "Protect this much memory from reclaim (MemoryLow); a percentage is of the memory limit (e.g. 256M, 25%)"
+    )]
+    memory_low: Option<String>,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "POLICY",
+        help = "How the memory limit is enforced: hard kills at the limit, soft throttles from 90% of it first, swap also allows swapping up to the limit"
+    )]
+    memory_policy: Option<MemoryPolicy>,
+
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_timespan,
+        help = "Stop the command after this much wall-clock time (e.g. 30m, 1h30m); sets RuntimeMaxSec"
+    )]
+    timeout: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        help = "Stop the command once it has written no output and used no CPU for this long"
+    )]
+    idle_timeout: Option<Duration>,
+
+    #[clap(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "text",
+        help = "Print the resolved systemd-run command without executing; --dry-run=json prints the resolved configuration, argv and the source of every value"
+    )]
+    dry_run: Option<DryRunFormat>,
+
     #[clap(
         long,
-        help = "Print the resolved systemd-run command without executing"
+        help = "Explain where every emitted property came from, without executing"
+    )]
+    explain: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "How to start the unit: exec systemd-run, or call the systemd user manager over D-Bus (default: systemd-run, or dbus with --stats or a timeout)"
     )]
-    dry_run: bool,
+    backend: Option<Backend>,
+
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "text",
+        help = "Enable resource accounting and report peak memory, CPU time, IO bytes and peak task count after the run; --stats=json reports JSON"
+    )]
+    stats: Option<StatsFormat>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        requires = "stats",
+        help = "Write the --stats report to PATH instead of stderr"
+    )]
+    stats_file: Option<PathBuf>,
 
     // Fine-grained path controls
-    #[arg(long = "rw", help = "Add read-write path access (can be repeated)")]
+    #[arg(
+        long = "rw",
+        value_parser = check_path_flag,
+        allow_hyphen_values = true,
+        help = "Add read-write path access; prefix with - if the path may be missing (can be repeated)"
+    )]
     rw_paths: Vec<String>,
 
-    #[arg(long = "ro", help = "Add read-only path access (can be repeated)")]
+    #[arg(
+        long = "ro",
+        value_parser = check_path_flag,
+        allow_hyphen_values = true,
+        help = "Add read-only path access; prefix with - if the path may be missing (can be repeated)"
+    )]
     ro_paths: Vec<String>,
 
-    #[arg(long, help = "Make path completely inaccessible (can be repeated)")]
+    #[arg(
+        long,
+        value_parser = check_path_flag,
+        allow_hyphen_values = true,
+        help = "Make path completely inaccessible; prefix with - if the path may be missing (can be repeated)"
+    )]
     inaccessible: Vec<String>,
 
+    #[arg(
+        long,
+        value_parser = BoolishValueParser::new(),
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        help = "Make secret-looking files in the project directory inaccessible: .env, *.pem, .npmrc and similar (default: off; on in the coding-agent profile)"
+    )]
+    mask_secrets: Option<bool>,
+
+    #[arg(
+        long = "secret-pattern",
+        value_name = "PATTERN",
+        value_parser = check_secret_pattern,
+        help = "Also mask files named like PATTERN with --mask-secrets, e.g. '*.secret' (can be repeated)"
+    )]
+    secret_patterns: Vec<String>,
+
+    #[arg(
+        long,
+        value_parser = BoolishValueParser::new(),
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        help = "Keep git hooks, config and other repository metadata read-only while the working tree stays writable (default: off; on in the coding-agent profile)"
+    )]
+    protect_git: Option<bool>,
+
     // Protection flags. Unset means "use the default (on)"; pass an explicit
     // value to override a profile or to turn the protection off.
     #[arg(long, value_parser = BoolishValueParser::new(), help = "Use private /tmp (default: true)")]
@@ -257,10 +1224,18 @@ struct Run {
     #[arg(long, value_parser = BoolishValueParser::new(), help = "Protect control groups (default: true)")]
     protect_control_groups: Option<bool>,
 
-    #[arg(long, help = "Protect home directories: none/yes/read-only/tmpfs")]
+    #[arg(
+        long,
+        value_parser = check_protect_home,
+        help = "Protect home directories: none/yes/read-only/tmpfs"
+    )]
     protect_home: Option<String>,
 
-    #[arg(long, help = "Protect system directories: none/yes/full/strict")]
+    #[arg(
+        long,
+        value_parser = check_protect_system,
+        help = "Protect system directories: none/yes/full/strict"
+    )]
     protect_system: Option<String>,
 
     // Network controls.
@@ -298,6 +1273,20 @@ struct Run {
     #[arg(long, help = "Restrictive preset: only current directory accessible")]
     current_dir_only: bool,
 
+    #[arg(
+        long,
+        value_name = "auto|git|cwd|PATH",
+        value_parser = check_project_root,
+        help = "Directory bound read-write when home is hidden: auto (the enclosing git repository, else the current directory), git, cwd, or a path (default: cwd; auto in the cargo, npm, uv and go profiles)"
+    )]
+    project_root: Option<String>,
+
+    #[arg(
+        long,
+        help = "Ignore any .playpen.toml in the current directory or its parents"
+    )]
+    no_project_file: bool,
+
     #[clap()]
     command_and_args: Vec<String>,
 }
@@ -307,15 +1296,43 @@ struct Run {
 /// The sandbox settings after a profile, the `--current-dir-only` preset and
 /// any explicit flags have all been merged. This is the single source of
 /// truth that `to_systemd_args` renders; nothing downstream re-reads the CLI.
+#[derive(Serialize)]
 struct Config {
     memory_max: Option<String>,
     memory_swap_max: Option<String>,
+    /// Throttling threshold (`MemoryHigh=`) and reclaim protection
+    /// (`MemoryLow=`). A percentage given on the command line has already
+    /// been resolved against `memory_max`.
+    memory_high: Option<String>,
+    memory_low: Option<String>,
     cpu_quota: Option<String>,
-    /// Per-direction disk I/O bandwidth ceilings (bytes/sec) for the block
-    /// device backing the working directory. `None` leaves that direction
-    /// unlimited.
+    /// Process and thread ceiling (`TasksMax=`).
+    tasks_max: Option<String>,
+    /// CPU and NUMA node pinning, as lists such as `0-3,8`.
+    allowed_cpus: Option<String>,
+    allowed_memory_nodes: Option<String>,
+    /// `CPUWeight=`, the nice level and `IOSchedulingClass=`.
+    cpu_weight: Option<String>,
+    nice: Option<i32>,
+    io_scheduling_class: Option<IoSchedulingClass>,
+    /// Per-direction disk I/O ceilings, in bytes/sec and operations/sec,
+    /// applied to each device behind the working directory and the
+    /// read-write binds. `None` leaves that limit unset.
     io_read_bandwidth_max: Option<String>,
     io_write_bandwidth_max: Option<String>,
+    io_read_iops_max: Option<String>,
+    io_write_iops_max: Option<String>,
+    /// `PATH=VALUE` limits from the command line, for one device each. They
+    /// win over the ceilings above on their own device.
+    io_device_limits: Vec<DeviceLimit>,
+    /// `IOWeight=`, 1 to 10000.
+    io_weight: Option<String>,
+    /// Turn on memory, CPU, IO and task accounting so `--stats` has counters
+    /// to read once the unit stops.
+    accounting: bool,
+    /// Wall-clock limit (`RuntimeMaxSec=`), a systemd time span such as
+    /// `30m`.
+    runtime_max: Option<String>,
     /// systemd `ProtectHome` value (`yes`/`read-only`/`tmpfs`); `None` leaves
     /// the home directory unrestricted.
     protect_home: Option<String>,
@@ -327,9 +1344,27 @@ struct Config {
     bind_paths: Vec<String>,
     bind_ro_paths: Vec<String>,
     inaccessible_paths: Vec<String>,
-    /// Bind-mount the current directory read-write. Needed whenever the home
+    /// Add the files in the project directory whose names match
+    /// `secret_patterns` to `inaccessible_paths`.
+    mask_secrets: bool,
+    secret_patterns: Vec<String>,
+    /// Re-bind the git metadata that decides what git runs (hooks, config
+    /// and the like) read-only over the writable binds, and the paths that
+    /// turned out to be. The git directories holding them are bound onto
+    /// themselves, so they cannot be moved aside.
+    protect_git: bool,
+    protected_git_dirs: Vec<String>,
+    protected_git_paths: Vec<String>,
+    /// The project file in use and the record of trusted ones, re-bound
+    /// read-only so the command cannot loosen its own next run.
+    protected_config_paths: Vec<String>,
+    /// Bind-mount the project directory read-write. Needed whenever the home
     /// directory is hidden, so the project being worked on stays reachable.
     bind_cwd: bool,
+    /// Which directory `bind_cwd` binds (`auto`, `git`, `cwd` or a path, as
+    /// for `--project-root`), and the directory that turned out to be.
+    project_root: String,
+    project_dir: PathBuf,
     /// Network namespace isolation. `Some(true)` emits `PrivateNetwork=yes`,
     /// `Some(false)` emits `PrivateNetwork=no`, `None` emits nothing.
     private_network: Option<bool>,
@@ -339,23 +1374,187 @@ struct Config {
     /// `SocketBindAllow=` / `SocketBindDeny=` rules, in CLI order.
     socket_bind_allow: Vec<String>,
     socket_bind_deny: Vec<String>,
+    /// Environment forwarding; see the `environment` module. `setenv` holds
+    /// `NAME=VALUE` assignments from `--env-file` and `--setenv`, in order.
+    capture_env: bool,
+    capture_path: bool,
+    env_allow: Vec<String>,
+    env_deny: Vec<String>,
+    setenv: Vec<String>,
+    /// Every value assigned while resolving, keyed by field, in the order
+    /// the layers applied them. For a scalar the last entry won; for a list
+    /// every entry is an element.
+    #[serde(skip)]
+    origins: BTreeMap<&'static str, Vec<Origin>>,
+    /// Profile and project-file paths left out because they do not exist.
+    #[serde(skip)]
+    skipped_paths: Vec<SkippedPath>,
+    /// Symlink targets bound so that a bound link still resolves.
+    #[serde(skip)]
+    symlink_targets: Vec<SymlinkTarget>,
+    /// Directories the secret search left out, which masking does not cover.
+    #[serde(skip)]
+    unsearched_dirs: Vec<secrets::Unsearched>,
+}
+
+/// A configuration layer, as named in `--dry-run=json` output.
+#[derive(Debug, Clone)]
+enum Layer {
+    Default,
+    Profile(String),
+    ProjectFile(PathBuf),
+    Preset,
+    Cli,
+}
+
+impl std::fmt::Display for Layer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Layer::Default => write!(f, "default"),
+            Layer::Profile(name) => write!(f, "profile:{}", name),
+            Layer::ProjectFile(path) => write!(f, "project:{}", path.display()),
+            Layer::Preset => write!(f, "preset:current-dir-only"),
+            Layer::Cli => write!(f, "cli"),
+        }
+    }
+}
+
+impl Serialize for Layer {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// One value a layer assigned to a `Config` field.
+#[derive(Debug, Clone, Serialize)]
+struct Origin {
+    source: Layer,
+    value: String,
+}
+
+/// The four per-device disk limits systemd offers.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum IoLimit {
+    ReadBandwidth,
+    WriteBandwidth,
+    ReadIops,
+    WriteIops,
+}
+
+impl IoLimit {
+    const ALL: [IoLimit; 4] = [
+        IoLimit::ReadBandwidth,
+        IoLimit::WriteBandwidth,
+        IoLimit::ReadIops,
+        IoLimit::WriteIops,
+    ];
+
+    /// The `Config` field holding the ceiling for every device.
+    fn field(self) -> &'static str {
+        match self {
+            IoLimit::ReadBandwidth => "io_read_bandwidth_max",
+            IoLimit::WriteBandwidth => "io_write_bandwidth_max",
+            IoLimit::ReadIops => "io_read_iops_max",
+            IoLimit::WriteIops => "io_write_iops_max",
+        }
+    }
+
+    fn property(self) -> &'static str {
+        match self {
+            IoLimit::ReadBandwidth => "IOReadBandwidthMax",
+            IoLimit::WriteBandwidth => "IOWriteBandwidthMax",
+            IoLimit::ReadIops => "IOReadIOPSMax",
+            IoLimit::WriteIops => "IOWriteIOPSMax",
+        }
+    }
+}
+
+/// A disk limit for the device behind one path (`--disk-write /data=20M`).
+#[derive(Debug, Clone, Serialize)]
+struct DeviceLimit {
+    kind: IoLimit,
+    /// Absolute; checked to exist when the flag was parsed.
+    path: String,
+    value: String,
+}
+
+/// The host side of a `BindPaths=` entry (`[-]SOURCE[:DEST[:OPTIONS]]`).
+fn bind_source(entry: &str) -> &str {
+    let entry = entry.strip_prefix('-').unwrap_or(entry);
+    entry.split(':').next().unwrap_or(entry)
+}
+
+/// The trees `ProtectHome=` applies to.
+const HOME_TREES: [&str; 3] = ["/home", "/root", "/run/user"];
+
+/// A symlink target bound because the link a layer bound points into a
+/// tree the sandbox hides.
+#[derive(Debug, Clone, Serialize)]
+struct SymlinkTarget {
+    field: &'static str,
+    source: Layer,
+    link: String,
+    target: String,
+}
+
+/// Longest symlink chain followed, as the kernel's own limit.
+const MAX_SYMLINK_HOPS: usize = 40;
+
+/// Each path `path` leads to as a symlink: the target of every link in the
+/// chain, with its directory resolved, then the fully resolved path if a
+/// symlinked parent directory makes it differ. Empty for a plain path.
+fn symlink_targets(path: &Path) -> Vec<PathBuf> {
+    let mut targets = Vec::new();
+    let mut current = path.to_path_buf();
+    for _ in 0..MAX_SYMLINK_HOPS {
+        let Ok(target) = std::fs::read_link(&current) else {
+            break;
+        };
+        let target = resolve_path(&current.parent().unwrap_or(Path::new("/")).join(target));
+        targets.push(target.clone());
+        current = target;
+    }
+    if let Ok(real) = std::fs::canonicalize(path) {
+        if real != path && !targets.contains(&real) {
+            targets.push(real);
+        }
+    }
+    targets
+}
+
+/// A path a layer asked for that was dropped because it does not exist.
+#[derive(Debug, Clone, Serialize)]
+struct SkippedPath {
+    field: &'static str,
+    source: Layer,
+    path: String,
 }
 
 impl Config {
-    /// Merge CLI arguments into the final sandbox configuration.
-    ///
-    /// Precedence, lowest to highest: built-in defaults, then `--profile`,
-    /// then `--current-dir-only`, then explicit per-setting flags. Command-line
-    /// order is irrelevant — an explicit flag always beats the profile. Path
-    /// flags (`--rw`/`--ro`/`--inaccessible`) accumulate rather than override.
-    fn resolve(cli: &Run) -> Config {
-        // Defaults: the four namespace protections are on; nothing else set.
+    /// The configuration before any layer applies: the four namespace
+    /// protections on, no limits, nothing bound.
+    fn defaults() -> Config {
         let mut c = Config {
             memory_max: None,
             memory_swap_max: None,
+            memory_high: None,
+            memory_low: None,
             cpu_quota: None,
+            tasks_max: None,
+            allowed_cpus: None,
+            allowed_memory_nodes: None,
+            cpu_weight: None,
+            nice: None,
+            io_scheduling_class: None,
             io_read_bandwidth_max: None,
             io_write_bandwidth_max: None,
+            io_read_iops_max: None,
+            io_write_iops_max: None,
+            io_device_limits: Vec::new(),
+            io_weight: None,
+            accounting: false,
+            runtime_max: None,
             protect_home: None,
             protect_system: None,
             private_tmp: true,
@@ -365,191 +1564,1096 @@ impl Config {
             bind_paths: Vec::new(),
             bind_ro_paths: Vec::new(),
             inaccessible_paths: Vec::new(),
+            mask_secrets: false,
+            secret_patterns: secrets::DEFAULT_PATTERNS
+                .iter()
+                .map(|p| p.to_string())
+                .collect(),
+            protect_git: false,
+            protected_git_dirs: Vec::new(),
+            protected_git_paths: Vec::new(),
+            protected_config_paths: Vec::new(),
             bind_cwd: false,
+            project_root: "cwd".to_string(),
+            project_dir: PathBuf::new(),
             private_network: None,
             ip_allow: Vec::new(),
             ip_deny: Vec::new(),
             socket_bind_allow: Vec::new(),
             socket_bind_deny: Vec::new(),
+            capture_env: false,
+            capture_path: true,
+            env_allow: Vec::new(),
+            env_deny: Vec::new(),
+            setenv: Vec::new(),
+            origins: BTreeMap::new(),
+            skipped_paths: Vec::new(),
+            symlink_targets: Vec::new(),
+            unsearched_dirs: Vec::new(),
+        };
+        for field in [
+            "private_tmp",
+            "private_devices",
+            "protect_kernel_tunables",
+            "protect_control_groups",
+        ] {
+            c.record(field, &Layer::Default, true);
+        }
+
+        c
+    }
+
+    /// Merge CLI arguments into the final sandbox configuration.
+    ///
+    /// Precedence, lowest to highest: built-in defaults, then `--profile`,
+    /// then the project's `.playpen.toml`, then `--current-dir-only`, then
+    /// explicit per-setting flags. Command-line order is irrelevant — an
+    /// explicit flag always beats the profile. Path settings
+    /// (`--rw`/`--ro`/`--inaccessible` and their project-file equivalents)
+    /// accumulate rather than override.
+    fn resolve(cli: &Run, profiles: &[Profile], project: Option<&ProjectFile>) -> Result<Config> {
+        let mut c = Config::defaults();
+
+        // Profile baseline. An explicit --profile beats the project's choice;
+        // repeated --profile flags are layered left to right with the same
+        // rules as `extends`: later limits win, paths add up.
+        let names: Vec<&str> = if cli.profile.is_empty() {
+            project
+                .and_then(|p| p.profile.as_deref())
+                .into_iter()
+                .collect()
+        } else {
+            cli.profile.iter().map(String::as_str).collect()
+        };
+        let active: Vec<&Profile> = names
+            .into_iter()
+            .map(|name| lookup_profile(profiles, name))
+            .collect();
+
+        // `$PROJECT_ROOT` in any layer's paths is the directory the winning
+        // `project_root` picks, so settle that before expanding them.
+        let mode = cli
+            .project_root
+            .clone()
+            .or_else(|| project.and_then(|p| p.settings.project_root.clone()))
+            .or_else(|| {
+                active
+                    .iter()
+                    .rev()
+                    .find_map(|p| p.settings.project_root.clone())
+            })
+            .unwrap_or_else(|| c.project_root.clone());
+        let cwd = std::env::current_dir().context("failed to read the current directory")?;
+        c.project_dir = project::locate(&expand_path(&mode), &cwd)?;
+        path_vars::set_project_root(&c.project_dir);
+        let project = project.map(ProjectFile::with_resolved_paths);
+        let project = project.as_ref();
+
+        // The layer that last turned secret masking on or off; masked files
+        // are credited to it.
+        let mut mask_layer = None;
+        for p in &active {
+            let layer = Layer::Profile(p.name.clone());
+            c.apply_settings(&p.settings, &layer);
+            if p.settings.mask_secrets.is_some() {
+                mask_layer = Some(layer);
+            }
+        }
+
+        // Project file: the repository's own policy, layered over the profile.
+        // Like a profile, it binds the project directory so the project stays
+        // reachable if it (or the profile) hides home.
+        if let Some(p) = project {
+            let layer = Layer::ProjectFile(p.path.clone());
+            c.apply_settings(&p.settings, &layer);
+            for path in &p.inaccessible_paths {
+                c.push_if_exists("inaccessible_paths", &layer, path, |c| {
+                    &mut c.inaccessible_paths
+                });
+            }
+            c.ip_allow.extend(p.ip_allow.iter().cloned());
+            c.record_all("ip_allow", &layer, &p.ip_allow);
+            c.ip_deny.extend(p.ip_deny.iter().cloned());
+            c.record_all("ip_deny", &layer, &p.ip_deny);
+            c.socket_bind_allow
+                .extend(p.socket_bind_allow.iter().cloned());
+            c.record_all("socket_bind_allow", &layer, &p.socket_bind_allow);
+            c.socket_bind_deny
+                .extend(p.socket_bind_deny.iter().cloned());
+            c.record_all("socket_bind_deny", &layer, &p.socket_bind_deny);
+            if p.settings.mask_secrets.is_some() {
+                mask_layer = Some(layer);
+            }
+        }
+
+        // The --current-dir-only preset: hide home, keep only the cwd. This is
+        // deliberately a *filesystem*-only preset — it does not touch network
+        // settings, so a user who picks it for filesystem reasons is not
+        // surprised by network failures. Compose with --private-network for
+        // both.
+        if cli.current_dir_only {
+            c.protect_home = Some("tmpfs".to_string());
+            c.record("protect_home", &Layer::Preset, "tmpfs");
+            c.bind_project(&Layer::Preset);
+        }
+
+        // Explicit flags override the profile and preset above.
+        let cli_layer = Layer::Cli;
+        if let Some(v) = &cli.memory_limit {
+            c.memory_max = Some(v.clone());
+            c.record("memory_max", &cli_layer, v);
+        }
+        if let Some(v) = &cli.cpu_limit {
+            c.cpu_quota = Some(v.clone());
+            c.record("cpu_quota", &cli_layer, v);
+        }
+        // Disk I/O: --disk-limit seeds both directions; a direction-specific
+        // flag then overrides its own side.
+        for v in &cli.disk_limit {
+            c.set_io_limit(IoLimit::ReadBandwidth, &cli_layer, v);
+            c.set_io_limit(IoLimit::WriteBandwidth, &cli_layer, v);
+        }
+        for v in &cli.disk_read {
+            c.set_io_limit(IoLimit::ReadBandwidth, &cli_layer, v);
+        }
+        for v in &cli.disk_write {
+            c.set_io_limit(IoLimit::WriteBandwidth, &cli_layer, v);
+        }
+        for v in &cli.disk_read_iops {
+            c.set_io_limit(IoLimit::ReadIops, &cli_layer, v);
+        }
+        for v in &cli.disk_write_iops {
+            c.set_io_limit(IoLimit::WriteIops, &cli_layer, v);
+        }
+        if let Some(v) = &cli.io_weight {
+            c.io_weight = Some(v.clone());
+            c.record("io_weight", &cli_layer, v);
+        }
+        // The policy's settings come first so the explicit flags below can
+        // override them. Both are relative to the memory limit, so there
+        // has to be one.
+        if let Some(policy @ (MemoryPolicy::Soft | MemoryPolicy::Swap)) = cli.memory_policy {
+            if !c.memory_max.as_deref().is_some_and(finite_limit) {
+                let name = policy.to_possible_value().expect("no skipped variants");
+                bail!(
+                    "--memory-policy {} needs a memory limit; set one with -m/--memory-limit",
+                    name.get_name()
+                );
+            }
+        }
+        match cli.memory_policy {
+            Some(MemoryPolicy::Soft) => {
+                c.memory_high = Some("90%".to_string());
+                c.record("memory_high", &cli_layer, "90% (--memory-policy soft)");
+            }
+            Some(MemoryPolicy::Swap) => {
+                c.memory_high = Some("90%".to_string());
+                c.record("memory_high", &cli_layer, "90% (--memory-policy swap)");
+                if let Some(max) = c.memory_max.clone() {
+                    c.record(
+                        "memory_swap_max",
+                        &cli_layer,
+                        format!("{} (--memory-policy swap)", max),
+                    );
+                    c.memory_swap_max = Some(max);
+                }
+            }
+            Some(MemoryPolicy::Hard) | None => {}
+        }
+        if let Some(v) = &cli.memory_swap_max {
+            c.memory_swap_max = Some(v.clone());
+            c.record("memory_swap_max", &cli_layer, v);
+        }
+        if let Some(v) = &cli.memory_high {
+            c.memory_high = Some(v.clone());
+            c.record("memory_high", &cli_layer, v);
+        }
+        if let Some(v) = &cli.memory_low {
+            c.memory_low = Some(v.clone());
+            c.record("memory_low", &cli_layer, v);
+        }
+        if let Some(v) = &cli.tasks_max {
+            c.tasks_max = Some(v.clone());
+            c.record("tasks_max", &cli_layer, v);
+        }
+        if let Some(v) = &cli.allowed_cpus {
+            c.allowed_cpus = Some(v.clone());
+            c.record("allowed_cpus", &cli_layer, v);
+        }
+        if let Some(v) = &cli.allowed_memory_nodes {
+            c.allowed_memory_nodes = Some(v.clone());
+            c.record("allowed_memory_nodes", &cli_layer, v);
+        }
+        if let Some(v) = &cli.cpu_weight {
+            c.cpu_weight = Some(v.clone());
+            c.record("cpu_weight", &cli_layer, v);
+        }
+        if let Some(v) = cli.nice {
+            c.nice = Some(v);
+            c.record("nice", &cli_layer, v);
+        }
+        if let Some(v) = cli.io_scheduling_class {
+            c.io_scheduling_class = Some(v);
+            c.record("io_scheduling_class", &cli_layer, v);
+        }
+        if let Some(v) = &cli.protect_home {
+            c.protect_home = normalize_protect(v);
+            c.record("protect_home", &cli_layer, v);
+        }
+        if let Some(v) = &cli.protect_system {
+            c.protect_system = normalize_protect(v);
+            c.record("protect_system", &cli_layer, v);
+        }
+        if let Some(v) = cli.private_tmp {
+            c.private_tmp = v;
+            c.record("private_tmp", &cli_layer, v);
+        }
+        if let Some(v) = cli.private_devices {
+            c.private_devices = v;
+            c.record("private_devices", &cli_layer, v);
+        }
+        if let Some(v) = cli.protect_kernel_tunables {
+            c.protect_kernel_tunables = v;
+            c.record("protect_kernel_tunables", &cli_layer, v);
+        }
+        if let Some(v) = cli.protect_control_groups {
+            c.protect_control_groups = v;
+            c.record("protect_control_groups", &cli_layer, v);
+        }
+        if let Some(v) = cli.private_network {
+            c.private_network = Some(v);
+            c.record("private_network", &cli_layer, v);
+        }
+        if let Some(v) = &cli.timeout {
+            c.runtime_max = Some(v.clone());
+            c.record("runtime_max", &cli_layer, v);
+        }
+        // The idle watchdog reads the unit's CPU counter.
+        if cli.stats.is_some() || cli.idle_timeout.is_some() {
+            c.accounting = true;
+            c.record("accounting", &cli_layer, true);
+        }
+
+        // Path flags accumulate on top of any profile paths. They are
+        // checked here rather than by clap, since `$PROJECT_ROOT` in them is
+        // only known now.
+        let checked = |flag: &str, values: &[String], check: Check| -> Result<Vec<String>> {
+            values
+                .iter()
+                .map(|v| {
+                    check(v).map_err(|e| anyhow!("invalid value '{}' for '{}': {}", v, flag, e))
+                })
+                .collect()
         };
+        let rw_paths = checked("--rw", &cli.rw_paths, check_bind_path)?;
+        c.bind_paths.extend(rw_paths.iter().cloned());
+        c.record_all("bind_paths", &cli_layer, &rw_paths);
+        let ro_paths = checked("--ro", &cli.ro_paths, check_bind_path)?;
+        c.bind_ro_paths.extend(ro_paths.iter().cloned());
+        c.record_all("bind_ro_paths", &cli_layer, &ro_paths);
+        let inaccessible = checked("--inaccessible", &cli.inaccessible, check_path)?;
+        c.inaccessible_paths.extend(inaccessible.iter().cloned());
+        c.record_all("inaccessible_paths", &cli_layer, &inaccessible);
+        if let Some(v) = &cli.project_root {
+            c.project_root = v.clone();
+            // With nothing bound there is nothing to steer, and `--explain`
+            // lists the setting as not emitted.
+            let field = if c.bind_cwd {
+                "bind_cwd"
+            } else {
+                "project_root"
+            };
+            c.record(field, &cli_layer, v);
+        }
+        c.ip_allow.extend(cli.ip_allow.iter().cloned());
+        c.record_all("ip_allow", &cli_layer, &cli.ip_allow);
+        c.ip_deny.extend(cli.ip_deny.iter().cloned());
+        c.record_all("ip_deny", &cli_layer, &cli.ip_deny);
+        c.socket_bind_allow
+            .extend(cli.socket_bind_allow.iter().cloned());
+        c.record_all("socket_bind_allow", &cli_layer, &cli.socket_bind_allow);
+        c.socket_bind_deny
+            .extend(cli.socket_bind_deny.iter().cloned());
+        c.record_all("socket_bind_deny", &cli_layer, &cli.socket_bind_deny);
 
-        // Profile baseline.
-        let profile = cli.profile.as_deref().map(lookup_profile);
-        if let Some(p) = profile {
-            c.memory_max = p.memory_limit.map(String::from);
-            c.cpu_quota = p.cpu_quota.map(String::from);
-            c.memory_swap_max = p.memory_swap_max.map(String::from);
-            c.protect_home = Some(p.protect_home.to_string());
-            c.private_network = p.private_network;
-            c.bind_cwd = true;
-            for path in p.rw_paths {
-                push_if_exists(&mut c.bind_paths, path);
+        // Environment: patterns accumulate like paths. Assignments apply in
+        // order, env files first, so --setenv has the last word.
+        c.capture_env = cli.capture_env;
+        c.capture_path = cli.capture_path;
+        c.env_allow.extend(cli.env_allow.iter().cloned());
+        c.record_all("env_allow", &cli_layer, &cli.env_allow);
+        c.env_deny.extend(cli.env_deny.iter().cloned());
+        c.record_all("env_deny", &cli_layer, &cli.env_deny);
+        for file in &cli.env_file {
+            for v in &file.vars {
+                c.setenv.push(v.clone());
+                c.record(
+                    "setenv",
+                    &cli_layer,
+                    format!("{} (--env-file {})", v, file.path.display()),
+                );
             }
-            for path in p.ro_paths {
-                push_if_exists(&mut c.bind_ro_paths, path);
+        }
+        c.setenv.extend(cli.setenv.iter().cloned());
+        c.record_all("setenv", &cli_layer, &cli.setenv);
+
+        // Secret masking looks at the project directory once every layer
+        // has had its say about the patterns and the project root.
+        if let Some(v) = cli.mask_secrets {
+            c.mask_secrets = v;
+            mask_layer = Some(cli_layer.clone());
+        }
+        c.secret_patterns
+            .extend(cli.secret_patterns.iter().cloned());
+        c.record_all("secret_patterns", &cli_layer, &cli.secret_patterns);
+        if c.bind_cwd {
+            c.bind_linked_git_dirs();
+        }
+        if let Some(v) = cli.protect_git {
+            c.protect_git = v;
+            c.record("protect_git", &cli_layer, v);
+        }
+        if c.protect_git {
+            c.protect_git_metadata();
+        }
+        if let Some(p) = project {
+            c.protect_project_file(&p.path);
+        }
+        c.bind_symlink_targets();
+        if let Some(layer) = mask_layer.filter(|_| c.mask_secrets) {
+            let found = secrets::find(&c.project_dir, &c.secret_patterns);
+            c.unsearched_dirs = found.unsearched;
+            for path in found.files {
+                let path = path.display().to_string();
+                if !c.inaccessible_paths.contains(&path) {
+                    c.record("inaccessible_paths", &layer, &path);
+                    c.inaccessible_paths.push(path);
+                }
             }
         }
 
-        // The --current-dir-only preset: hide home, keep only the cwd. This is
-        // deliberately a *filesystem*-only preset — it does not touch network
-        // settings, so a user who picks it for filesystem reasons is not
-        // surprised by network failures. Compose with --private-network for
-        // both.
-        if cli.current_dir_only {
-            c.protect_home = Some("tmpfs".to_string());
-            c.bind_cwd = true;
+        // A bare memory limit gets a hard ceiling by disabling swap. Profiles
+        // pick their own swap policy, so this default applies only when no
+        // profile is active.
+        if c.memory_max.is_some() && c.memory_swap_max.is_none() && active.is_empty() {
+            c.memory_swap_max = Some("0".to_string());
+            c.record("memory_swap_max", &Layer::Default, "0");
+        }
+
+        // Cores and shares of the machine become a plain percentage here, so
+        // everything downstream sees what systemd will.
+        if let Some(v) = &c.cpu_quota {
+            let host_cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+            c.cpu_quota = Some(cpu_quota_percent(v, host_cpus));
+        }
+
+        // Shares of physical memory become sizes, the limit first since the
+        // soft thresholds are relative to whatever limit won.
+        let ram = size::total_ram().ok();
+        if let Some(v) = &c.memory_max {
+            c.memory_max = Some(size::resolve_ram_share(v, ram));
+        }
+        let max = c.memory_max.clone();
+        for (flag, v) in [
+            ("memory-high", &mut c.memory_high),
+            ("memory-low", &mut c.memory_low),
+        ] {
+            if let Some(v) = v {
+                *v = size::resolve_ram_share(&share_of_limit(flag, v, max.as_deref())?, ram);
+            }
+        }
+        if let Some(v) = &c.memory_swap_max {
+            c.memory_swap_max = Some(size::resolve_ram_share(v, ram));
+        }
+
+        Ok(c)
+    }
+
+    /// Note that `layer` assigned `value` to `field`.
+    fn record(&mut self, field: &'static str, layer: &Layer, value: impl ToString) {
+        self.origins.entry(field).or_default().push(Origin {
+            source: layer.clone(),
+            value: value.to_string(),
+        });
+    }
+
+    /// Bind the project directory on behalf of `layer`. Every profile and
+    /// project file asks for the bind, so `layer` is only credited when it
+    /// changes which directory that is; otherwise the layer that first asked
+    /// keeps it and `--explain` shows no override.
+    fn bind_project(&mut self, layer: &Layer) {
+        let unchanged = self.bind_cwd
+            && self
+                .origins
+                .get("bind_cwd")
+                .and_then(|o| o.last())
+                .is_some_and(|o| o.value == self.project_root);
+        self.bind_cwd = true;
+        if !unchanged {
+            let root = self.project_root.clone();
+            self.record("bind_cwd", layer, root);
+        }
+    }
+
+    /// Layer a profile's or project file's settings over the configuration:
+    /// each value it sets wins, and its paths and patterns are added. Either
+    /// one also binds the project directory, so the project stays reachable
+    /// if it (or a layer below) hides home.
+    fn apply_settings(&mut self, s: &Settings, layer: &Layer) {
+        if let Some(v) = &s.memory_limit {
+            self.memory_max = Some(v.clone());
+            self.record("memory_max", layer, v);
+        }
+        if let Some(v) = &s.cpu_quota {
+            self.cpu_quota = Some(v.clone());
+            self.record("cpu_quota", layer, v);
+        }
+        if let Some(v) = &s.memory_swap_max {
+            self.memory_swap_max = Some(v.clone());
+            self.record("memory_swap_max", layer, v);
+        }
+        if let Some(v) = &s.tasks_max {
+            self.tasks_max = Some(v.clone());
+            self.record("tasks_max", layer, v);
+        }
+        if let Some(v) = &s.allowed_cpus {
+            self.allowed_cpus = Some(v.clone());
+            self.record("allowed_cpus", layer, v);
+        }
+        if let Some(v) = &s.allowed_memory_nodes {
+            self.allowed_memory_nodes = Some(v.clone());
+            self.record("allowed_memory_nodes", layer, v);
+        }
+        if let Some(v) = &s.cpu_weight {
+            self.cpu_weight = Some(v.clone());
+            self.record("cpu_weight", layer, v);
+        }
+        if let Some(v) = s.nice {
+            self.nice = Some(v);
+            self.record("nice", layer, v);
+        }
+        if let Some(v) = s.io_scheduling_class {
+            self.io_scheduling_class = Some(v);
+            self.record("io_scheduling_class", layer, v);
         }
+        if let Some(v) = &s.disk_read {
+            self.io_read_bandwidth_max = Some(v.clone());
+            self.record("io_read_bandwidth_max", layer, v);
+        }
+        if let Some(v) = &s.disk_write {
+            self.io_write_bandwidth_max = Some(v.clone());
+            self.record("io_write_bandwidth_max", layer, v);
+        }
+        if let Some(v) = &s.disk_read_iops {
+            self.io_read_iops_max = Some(v.clone());
+            self.record("io_read_iops_max", layer, v);
+        }
+        if let Some(v) = &s.disk_write_iops {
+            self.io_write_iops_max = Some(v.clone());
+            self.record("io_write_iops_max", layer, v);
+        }
+        if let Some(v) = &s.io_weight {
+            self.io_weight = Some(v.clone());
+            self.record("io_weight", layer, v);
+        }
+        if let Some(v) = &s.timeout {
+            self.runtime_max = Some(v.clone());
+            self.record("runtime_max", layer, v);
+        }
+        if let Some(v) = &s.protect_home {
+            self.protect_home = normalize_protect(v);
+            self.record("protect_home", layer, v);
+        }
+        if let Some(v) = &s.protect_system {
+            self.protect_system = normalize_protect(v);
+            self.record("protect_system", layer, v);
+        }
+        if let Some(v) = s.private_network {
+            self.private_network = Some(v);
+            self.record("private_network", layer, v);
+        }
+        if let Some(v) = &s.project_root {
+            self.project_root = v.clone();
+        }
+        self.bind_project(layer);
+        for path in &s.rw_paths {
+            self.push_if_exists("bind_paths", layer, path, |c| &mut c.bind_paths);
+        }
+        for path in &s.ro_paths {
+            self.push_if_exists("bind_ro_paths", layer, path, |c| &mut c.bind_ro_paths);
+        }
+        self.env_allow.extend(s.env_allow.iter().cloned());
+        self.record_all("env_allow", layer, &s.env_allow);
+        self.env_deny.extend(s.env_deny.iter().cloned());
+        self.record_all("env_deny", layer, &s.env_deny);
+        if let Some(v) = s.mask_secrets {
+            self.mask_secrets = v;
+        }
+        self.secret_patterns
+            .extend(s.secret_patterns.iter().cloned());
+        self.record_all("secret_patterns", layer, &s.secret_patterns);
+        if let Some(v) = s.protect_git {
+            self.protect_git = v;
+            self.record("protect_git", layer, v);
+        }
+    }
 
-        // Explicit flags override the profile and preset above.
-        if let Some(v) = &cli.memory_limit {
-            c.memory_max = Some(v.clone());
+    /// Add a profile or project-file path to the list `field`, but only if it
+    /// currently exists. systemd-run refuses to start if asked to bind-mount
+    /// a missing path, so a profile that names, say, `$HOME/.cargo` on a
+    /// machine without Cargo simply skips it; the skip is remembered for
+    /// `--explain`. A path already in the list (two profiles sharing it) is
+    /// not added twice.
+    fn push_if_exists(&mut self, field: &'static str, layer: &Layer, raw: &str, list: ListField) {
+        let expanded = expand_path(raw);
+        if !Path::new(&expanded).exists() {
+            self.skipped_paths.push(SkippedPath {
+                field,
+                source: layer.clone(),
+                path: expanded,
+            });
+            return;
         }
-        if let Some(v) = &cli.cpu_limit {
-            c.cpu_quota = Some(v.clone());
+        if list(self).contains(&expanded) {
+            return;
         }
-        // Disk I/O: --disk-limit seeds both directions; a direction-specific
-        // flag then overrides its own side.
-        if let Some(v) = &cli.disk_limit {
-            c.io_read_bandwidth_max = Some(v.clone());
-            c.io_write_bandwidth_max = Some(v.clone());
+        list(self).push(expanded.clone());
+        self.record(field, layer, expanded);
+    }
+
+    /// Bind the git directories a worktree or submodule checkout at the
+    /// project root keeps outside it, so git still works in the sandbox.
+    /// They are credited to the layer that asked for the project bind.
+    fn bind_linked_git_dirs(&mut self) {
+        let layer = self
+            .origins
+            .get("bind_cwd")
+            .and_then(|o| o.last())
+            .map_or(Layer::Cli, |o| o.source.clone());
+        for dir in project::linked_git_dirs(&self.project_dir) {
+            let dir = dir.display().to_string();
+            let covered = self
+                .bind_paths
+                .iter()
+                .any(|p| Path::new(&dir).starts_with(bind_source(p)));
+            if !covered {
+                self.bind_paths.push(dir.clone());
+                self.record("bind_paths", &layer, dir);
+            }
         }
-        if let Some(v) = &cli.disk_read {
-            c.io_read_bandwidth_max = Some(v.clone());
+    }
+
+    /// Make the git metadata of the project's checkout read-only, where the
+    /// command could otherwise write it. Metadata under a hidden tree and
+    /// outside every read-write bind is left alone: the command cannot see
+    /// it, and binding it would make it visible.
+    fn protect_git_metadata(&mut self) {
+        let Some(root) = project::git_root(&self.project_dir) else {
+            return;
+        };
+        for dir in project::git_dirs(&root) {
+            if self.writable(&dir) {
+                self.protected_git_dirs.push(dir.display().to_string());
+            }
         }
-        if let Some(v) = &cli.disk_write {
-            c.io_write_bandwidth_max = Some(v.clone());
+        for path in project::protected_git_paths(&root) {
+            if self.reachable(&path) {
+                self.protected_git_paths.push(path.display().to_string());
+            }
         }
-        if let Some(v) = &cli.memory_swap_max {
-            c.memory_swap_max = Some(v.clone());
+    }
+
+    /// Make the project file `file` and the record of trusted project files
+    /// read-only, where the command could otherwise write them.
+    fn protect_project_file(&mut self, file: &Path) {
+        let layer = Layer::ProjectFile(file.to_path_buf());
+        let store = config_dir()
+            .map(|dir| trust::store_path(&dir))
+            .filter(|store| store.exists());
+        for path in std::iter::once(file.to_path_buf()).chain(store) {
+            if self.reachable(&path) {
+                let path = path.display().to_string();
+                self.protected_config_paths.push(path.clone());
+                self.record("protect_config", &layer, path);
+            }
         }
-        if let Some(v) = &cli.protect_home {
-            c.protect_home = normalize_protect(v);
+    }
+
+    /// Whether the command can see `path`: it is under a read-write bind, or
+    /// outside every hidden tree. Binding a path the command cannot see
+    /// would make it visible.
+    fn reachable(&self, path: &Path) -> bool {
+        self.under_read_write_bind(path) || !self.hidden(path)
+    }
+
+    /// Whether the command can write `path`: it is under a read-write bind,
+    /// or visible and left as writable as it is outside the sandbox. A
+    /// read-write bind of anything else would make it writable.
+    fn writable(&self, path: &Path) -> bool {
+        if self.under_read_write_bind(path) {
+            return true;
         }
-        if let Some(v) = &cli.protect_system {
-            c.protect_system = normalize_protect(v);
+        let protected = self.hidden(path)
+            || (self.protect_home.is_some() && HOME_TREES.iter().any(|t| path.starts_with(t)))
+            || self.protect_system.as_deref() == Some("strict")
+            || self
+                .bind_ro_paths
+                .iter()
+                .any(|p| path.starts_with(bind_source(p)));
+        !protected
+    }
+
+    fn under_read_write_bind(&self, path: &Path) -> bool {
+        (self.bind_cwd && path.starts_with(&self.project_dir))
+            || self
+                .bind_paths
+                .iter()
+                .any(|p| path.starts_with(bind_source(p)))
+    }
+
+    fn hidden(&self, path: &Path) -> bool {
+        self.hidden_trees()
+            .iter()
+            .any(|tree| path.starts_with(tree))
+    }
+
+    /// The trees the sandbox replaces with empty ones: home directories
+    /// under `ProtectHome=yes`/`tmpfs`, and `/tmp` under `PrivateTmp=`.
+    fn hidden_trees(&self) -> Vec<&'static Path> {
+        let mut trees = Vec::new();
+        if matches!(self.protect_home.as_deref(), Some("yes" | "tmpfs")) {
+            trees.extend(HOME_TREES.map(Path::new));
         }
-        if let Some(v) = cli.private_tmp {
-            c.private_tmp = v;
+        if self.private_tmp {
+            trees.extend(["/tmp", "/var/tmp"].map(Path::new));
         }
-        if let Some(v) = cli.private_devices {
-            c.private_devices = v;
+        trees
+    }
+
+    /// Bind the targets of symlinked bind paths. A bound link into a hidden
+    /// tree, such as a stow-managed `~/.claude.json` under a tmpfs home,
+    /// would otherwise dangle. Each target that is hidden and not already
+    /// under a bound path is added to the same list as its link, so it keeps
+    /// the link's access mode.
+    fn bind_symlink_targets(&mut self) {
+        let hidden = self.hidden_trees();
+        if hidden.is_empty() {
+            return;
         }
-        if let Some(v) = cli.protect_kernel_tunables {
-            c.protect_kernel_tunables = v;
+        let lists: [(&'static str, ListField); 2] = [
+            ("bind_paths", |c| &mut c.bind_paths),
+            ("bind_ro_paths", |c| &mut c.bind_ro_paths),
+        ];
+        for (field, list) in lists {
+            for entry in list(self).clone() {
+                let link = bind_source(&entry);
+                let source = self
+                    .origins
+                    .get(field)
+                    .and_then(|o| o.iter().find(|o| o.value == entry))
+                    .map_or(Layer::Cli, |o| o.source.clone());
+                for target in symlink_targets(Path::new(link)) {
+                    let covered = self.bind_cwd && target.starts_with(&self.project_dir);
+                    let covered = covered
+                        || self
+                            .bind_paths
+                            .iter()
+                            .chain(&self.bind_ro_paths)
+                            .any(|p| target.starts_with(bind_source(p)));
+                    if covered || !hidden.iter().any(|tree| target.starts_with(tree)) {
+                        continue;
+                    }
+                    let target = target.display().to_string();
+                    list(self).push(target.clone());
+                    self.record(field, &source, &target);
+                    self.symlink_targets.push(SymlinkTarget {
+                        field,
+                        source: source.clone(),
+                        link: link.to_string(),
+                        target,
+                    });
+                }
+            }
         }
-        if let Some(v) = cli.protect_control_groups {
-            c.protect_control_groups = v;
+    }
+
+    /// Apply a disk limit from the command line: a plain value sets the
+    /// ceiling for every device the command writes to, `PATH=VALUE` one
+    /// device's limit, replacing an earlier one for the same path.
+    fn set_io_limit(&mut self, kind: IoLimit, layer: &Layer, value: &str) {
+        match value.rsplit_once('=') {
+            Some((path, v)) => {
+                self.io_device_limits
+                    .retain(|d| !(d.kind == kind && d.path == path));
+                self.io_device_limits.push(DeviceLimit {
+                    kind,
+                    path: path.to_string(),
+                    value: v.to_string(),
+                });
+                self.record("io_device_limits", layer, format!("{} {}", path, v));
+            }
+            None => {
+                *self.io_ceiling_mut(kind) = Some(value.to_string());
+                self.record(kind.field(), layer, value);
+            }
         }
-        if let Some(v) = cli.private_network {
-            c.private_network = Some(v);
+    }
+
+    fn io_ceiling(&self, kind: IoLimit) -> &Option<String> {
+        match kind {
+            IoLimit::ReadBandwidth => &self.io_read_bandwidth_max,
+            IoLimit::WriteBandwidth => &self.io_write_bandwidth_max,
+            IoLimit::ReadIops => &self.io_read_iops_max,
+            IoLimit::WriteIops => &self.io_write_iops_max,
         }
+    }
 
-        // Path flags accumulate on top of any profile paths.
-        c.bind_paths.extend(cli.rw_paths.iter().cloned());
-        c.bind_ro_paths.extend(cli.ro_paths.iter().cloned());
-        c.inaccessible_paths
-            .extend(cli.inaccessible.iter().cloned());
-        c.ip_allow.extend(cli.ip_allow.iter().cloned());
-        c.ip_deny.extend(cli.ip_deny.iter().cloned());
-        c.socket_bind_allow
-            .extend(cli.socket_bind_allow.iter().cloned());
-        c.socket_bind_deny
-            .extend(cli.socket_bind_deny.iter().cloned());
+    fn io_ceiling_mut(&mut self, kind: IoLimit) -> &mut Option<String> {
+        match kind {
+            IoLimit::ReadBandwidth => &mut self.io_read_bandwidth_max,
+            IoLimit::WriteBandwidth => &mut self.io_write_bandwidth_max,
+            IoLimit::ReadIops => &mut self.io_read_iops_max,
+            IoLimit::WriteIops => &mut self.io_write_iops_max,
+        }
+    }
 
-        // A bare memory limit gets a hard ceiling by disabling swap. Profiles
-        // pick their own swap policy, so this default applies only when no
-        // profile is active.
-        if c.memory_max.is_some() && c.memory_swap_max.is_none() && profile.is_none() {
-            c.memory_swap_max = Some("0".to_string());
+    /// The disk limit properties. systemd limits a device by naming any
+    /// path on it, so each ceiling is emitted once per distinct device
+    /// behind the project directory and the read-write binds — where a
+    /// sandboxed command does its real I/O. `PATH=VALUE` limits follow and
+    /// take their device out of the ceiling's list.
+    fn io_limit_properties(&self) -> Vec<(&'static str, String)> {
+        use std::os::unix::fs::MetadataExt;
+        let device = |path: &str| std::fs::metadata(path).ok().map(|m| m.dev());
+
+        let mut targets = vec![self.project_dir.display().to_string()];
+        targets.extend(self.bind_paths.iter().map(|p| bind_source(p).to_string()));
+
+        let mut args = Vec::new();
+        for kind in IoLimit::ALL {
+            let explicit: Vec<&DeviceLimit> = self
+                .io_device_limits
+                .iter()
+                .filter(|d| d.kind == kind)
+                .collect();
+            let mut devices: Vec<u64> = explicit.iter().filter_map(|d| device(&d.path)).collect();
+            if let Some(v) = self.io_ceiling(kind) {
+                for path in &targets {
+                    let Some(dev) = device(path) else { continue };
+                    if devices.contains(&dev) {
+                        continue;
+                    }
+                    devices.push(dev);
+                    args.push((kind.field(), format!("{}={} {}", kind.property(), path, v)));
+                }
+            }
+            for d in explicit {
+                args.push((
+                    "io_device_limits",
+                    format!("{}={} {}", kind.property(), d.path, d.value),
+                ));
+            }
         }
+        args
+    }
 
-        c
+    /// Note that `layer` appended each of `values` to the list `field`.
+    fn record_all(&mut self, field: &'static str, layer: &Layer, values: &[String]) {
+        for v in values {
+            self.record(field, layer, v);
+        }
     }
 
     /// Render the configuration as `systemd-run` `-p` property arguments.
     fn to_systemd_args(&self) -> Vec<String> {
+        self.properties()
+            .into_iter()
+            .map(|(_, property)| format!("-p{}", property))
+            .collect()
+    }
+
+    /// The unit properties this configuration sets, in emission order, each
+    /// paired with the `Config` field it comes from.
+    fn properties(&self) -> Vec<(&'static str, String)> {
         let mut args = Vec::new();
 
         if let Some(v) = &self.memory_max {
-            args.push(format!("-pMemoryMax={}", v));
+            args.push(("memory_max", format!("MemoryMax={}", v)));
+        }
+        if let Some(v) = &self.memory_high {
+            args.push(("memory_high", format!("MemoryHigh={}", v)));
+        }
+        if let Some(v) = &self.memory_low {
+            args.push(("memory_low", format!("MemoryLow={}", v)));
         }
         if let Some(v) = &self.memory_swap_max {
-            args.push(format!("-pMemorySwapMax={}", v));
+            args.push(("memory_swap_max", format!("MemorySwapMax={}", v)));
         }
         if let Some(v) = &self.cpu_quota {
-            args.push(format!("-pCPUQuota={}", v));
-            args.push(format!("-pCPUQuotaPeriodSec={}", DEFAULT_CPU_QUOTA_PERIOD));
-        }
-        // Disk I/O limits name the block device backing the working
-        // directory — where a sandboxed build does its real I/O. systemd
-        // resolves the path to its device, so passing the path is enough.
-        if self.io_read_bandwidth_max.is_some() || self.io_write_bandwidth_max.is_some() {
-            if let Ok(pwd) = std::env::current_dir() {
-                if let Some(v) = &self.io_read_bandwidth_max {
-                    args.push(format!("-pIOReadBandwidthMax={} {}", pwd.display(), v));
-                }
-                if let Some(v) = &self.io_write_bandwidth_max {
-                    args.push(format!("-pIOWriteBandwidthMax={} {}", pwd.display(), v));
-                }
+            args.push(("cpu_quota", format!("CPUQuota={}", v)));
+            args.push((
+                "cpu_quota_period",
+                format!("CPUQuotaPeriodSec={}", DEFAULT_CPU_QUOTA_PERIOD),
+            ));
+        }
+        if let Some(v) = &self.allowed_cpus {
+            args.push(("allowed_cpus", format!("AllowedCPUs={}", v)));
+        }
+        if let Some(v) = &self.allowed_memory_nodes {
+            args.push(("allowed_memory_nodes", format!("AllowedMemoryNodes={}", v)));
+        }
+        if let Some(v) = &self.cpu_weight {
+            args.push(("cpu_weight", format!("CPUWeight={}", v)));
+        }
+        if let Some(v) = &self.tasks_max {
+            args.push(("tasks_max", format!("TasksMax={}", v)));
+        }
+        if let Some(v) = self.nice {
+            args.push(("nice", format!("Nice={}", v)));
+        }
+        if let Some(v) = self.io_scheduling_class {
+            args.push(("io_scheduling_class", format!("IOSchedulingClass={}", v)));
+        }
+        if let Some(v) = &self.io_weight {
+            args.push(("io_weight", format!("IOWeight={}", v)));
+        }
+        args.extend(self.io_limit_properties());
+        if let Some(v) = &self.runtime_max {
+            args.push(("runtime_max", format!("RuntimeMaxSec={}", v)));
+        }
+        if self.accounting {
+            for property in [
+                "MemoryAccounting",
+                "CPUAccounting",
+                "IOAccounting",
+                "TasksAccounting",
+            ] {
+                args.push(("accounting", format!("{}=yes", property)));
             }
         }
         if self.private_tmp {
-            args.push("-pPrivateTmp=yes".to_string());
+            args.push(("private_tmp", "PrivateTmp=yes".to_string()));
         }
         if self.private_devices {
-            args.push("-pPrivateDevices=yes".to_string());
+            args.push(("private_devices", "PrivateDevices=yes".to_string()));
         }
         if self.protect_kernel_tunables {
-            args.push("-pProtectKernelTunables=yes".to_string());
+            args.push((
+                "protect_kernel_tunables",
+                "ProtectKernelTunables=yes".to_string(),
+            ));
         }
         if self.protect_control_groups {
-            args.push("-pProtectControlGroups=yes".to_string());
+            args.push((
+                "protect_control_groups",
+                "ProtectControlGroups=yes".to_string(),
+            ));
         }
         if let Some(v) = &self.protect_home {
-            args.push(format!("-pProtectHome={}", v));
+            args.push(("protect_home", format!("ProtectHome={}", v)));
         }
         if let Some(v) = &self.protect_system {
-            args.push(format!("-pProtectSystem={}", v));
+            args.push(("protect_system", format!("ProtectSystem={}", v)));
         }
 
         if let Some(v) = self.private_network {
-            args.push(format!(
-                "-pPrivateNetwork={}",
-                if v { "yes" } else { "no" }
+            args.push((
+                "private_network",
+                format!("PrivateNetwork={}", if v { "yes" } else { "no" }),
             ));
         }
         for v in &self.ip_allow {
-            args.push(format!("-pIPAddressAllow={}", v));
+            args.push(("ip_allow", format!("IPAddressAllow={}", v)));
         }
         for v in &self.ip_deny {
-            args.push(format!("-pIPAddressDeny={}", v));
+            args.push(("ip_deny", format!("IPAddressDeny={}", v)));
         }
         for v in &self.socket_bind_allow {
-            args.push(format!("-pSocketBindAllow={}", v));
+            args.push(("socket_bind_allow", format!("SocketBindAllow={}", v)));
         }
         for v in &self.socket_bind_deny {
-            args.push(format!("-pSocketBindDeny={}", v));
+            args.push(("socket_bind_deny", format!("SocketBindDeny={}", v)));
         }
 
         if self.bind_cwd {
-            if let Ok(pwd) = std::env::current_dir() {
-                args.push(format!("-pBindPaths={}", pwd.display()));
-            }
+            args.push((
+                "bind_cwd",
+                format!("BindPaths={}", self.project_dir.display()),
+            ));
         }
         for p in &self.bind_paths {
-            args.push(format!("-pBindPaths={}", p));
+            args.push(("bind_paths", format!("BindPaths={}", p)));
         }
         for p in &self.bind_ro_paths {
-            args.push(format!("-pBindReadOnlyPaths={}", p));
+            args.push(("bind_ro_paths", format!("BindReadOnlyPaths={}", p)));
+        }
+        // systemd mounts in order of path depth, not in the order given here,
+        // so these land on top of the shallower project bind they carve into.
+        for p in &self.protected_git_dirs {
+            args.push(("protect_git", format!("BindPaths={}", p)));
+        }
+        for p in &self.protected_git_paths {
+            args.push(("protect_git", format!("BindReadOnlyPaths={}", p)));
+        }
+        for p in &self.protected_config_paths {
+            args.push(("protect_config", format!("BindReadOnlyPaths={}", p)));
         }
         for p in &self.inaccessible_paths {
-            args.push(format!("-pInaccessiblePaths={}", p));
+            args.push(("inaccessible_paths", format!("InaccessiblePaths={}", p)));
         }
 
         args
     }
 }
 
+impl Config {
+    /// Describe, for `--explain`, which layer set each emitted property and
+    /// which lower-layer values it overrode, followed by settings a layer
+    /// turned off, symlink targets bound, directories the secret search left
+    /// out and profile paths that were skipped.
+    fn explain(&self) -> String {
+        let mut out = String::from("Properties:\n");
+        let properties = self.properties();
+        let width = properties.iter().map(|(_, p)| p.len()).max().unwrap_or(0);
+
+        for (field, property) in &properties {
+            let origins = self.origins.get(field).map(Vec::as_slice).unwrap_or(&[]);
+            let line = if LIST_FIELDS.contains(field) {
+                // A list element has exactly one origin: the layer that added it.
+                origins
+                    .iter()
+                    .find(|o| property.ends_with(&format!("={}", o.value)))
+                    .map(|o| o.source.to_string())
+            } else {
+                describe_winner(origins)
+            };
+            let line = line.unwrap_or_else(|| "default".to_string());
+            out.push_str(&format!("  {:width$}  {}\n", property, line));
+        }
+
+        // Settings whose final value emits nothing, e.g. `--private-tmp false`
+        // or `--protect-home none` over a profile's tmpfs.
+        let emitted: Vec<&str> = properties.iter().map(|(field, _)| *field).collect();
+        let disabled: Vec<(&&str, &Vec<Origin>)> = self
+            .origins
+            .iter()
+            .filter(|(field, _)| !emitted.contains(field) && !LIST_FIELDS.contains(field))
+            .collect();
+        if !disabled.is_empty() {
+            out.push_str("\nNot emitted:\n");
+            for (field, origins) in disabled {
+                let value = origins.last().map(|o| o.value.as_str()).unwrap_or("");
+                let line = describe_winner(origins).unwrap_or_default();
+                out.push_str(&format!("  {} = {}  {}\n", field, value, line));
+            }
+        }
+
+        if !self.symlink_targets.is_empty() {
+            out.push_str("\nSymlink targets bound:\n");
+            let width = self
+                .symlink_targets
+                .iter()
+                .map(|t| t.target.len())
+                .max()
+                .unwrap_or(0);
+            for t in &self.symlink_targets {
+                out.push_str(&format!(
+                    "  {:width$}  {} for {} from {}\n",
+                    t.target, t.field, t.link, t.source
+                ));
+            }
+        }
+
+        if !self.unsearched_dirs.is_empty() {
+            out.push_str("\nNot searched for secrets:\n");
+            let paths: Vec<String> = self
+                .unsearched_dirs
+                .iter()
+                .map(|u| u.path.display().to_string())
+                .collect();
+            let width = paths.iter().map(String::len).max().unwrap_or(0);
+            for (path, u) in paths.iter().zip(&self.unsearched_dirs) {
+                let why = match u.reason {
+                    secrets::Reason::Skipped => "skipped directory".to_string(),
+                    secrets::Reason::TooDeep => {
+                        format!("more than {} levels down", secrets::MAX_DEPTH)
+                    }
+                };
+                out.push_str(&format!("  {:width$}  {}\n", path, why));
+            }
+        }
+
+        if !self.skipped_paths.is_empty() {
+            out.push_str("\nSkipped paths (do not exist):\n");
+            let width = self
+                .skipped_paths
+                .iter()
+                .map(|s| s.path.len())
+                .max()
+                .unwrap_or(0);
+            for skipped in &self.skipped_paths {
+                out.push_str(&format!(
+                    "  {:width$}  {} from {}\n",
+                    skipped.path, skipped.field, skipped.source
+                ));
+            }
+        }
+
+        out
+    }
+}
+
+/// `Config` fields that hold lists; their values accumulate across layers
+/// instead of overriding each other.
+const LIST_FIELDS: &[&str] = &[
+    "bind_paths",
+    "bind_ro_paths",
+    "env_allow",
+    "env_deny",
+    "inaccessible_paths",
+    "io_device_limits",
+    "ip_allow",
+    "ip_deny",
+    "protect_config",
+    "secret_patterns",
+    "socket_bind_allow",
+    "socket_bind_deny",
+    "setenv",
+];
+
+/// "`<winning layer>` (overrides `<layer>=<value>`, ...)" for a scalar field.
+fn describe_winner(origins: &[Origin]) -> Option<String> {
+    let (winner, overridden) = origins.split_last()?;
+    let mut line = winner.source.to_string();
+    if !overridden.is_empty() {
+        let lower: Vec<String> = overridden
+            .iter()
+            .rev()
+            .map(|o| format!("{}={}", o.source, o.value))
+            .collect();
+        line.push_str(&format!(" (overrides {})", lower.join(", ")));
+    }
+    Some(line)
+}
+
+/// Check a `ProtectHome=` value, or `none` for no restriction.
+fn check_protect_home(s: &str) -> Result<String, String> {
+    check_protect(s, &["none", "yes", "read-only", "tmpfs"])
+}
+
+/// Check a `ProtectSystem=` value, or `none` for no restriction.
+fn check_protect_system(s: &str) -> Result<String, String> {
+    check_protect(s, &["none", "yes", "full", "strict"])
+}
+
+fn check_protect(s: &str, allowed: &[&str]) -> Result<String, String> {
+    if allowed.contains(&s) {
+        Ok(s.to_string())
+    } else {
+        Err(format!("{} is not one of {}", s, allowed.join(", ")))
+    }
+}
+
 /// Translate a `--protect-home`/`--protect-system` value into an emittable
 /// setting: the sentinel `none` means "do not restrict" (`None`).
 fn normalize_protect(value: &str) -> Option<String> {
@@ -560,6 +2664,49 @@ fn normalize_protect(value: &str) -> Option<String> {
     }
 }
 
+/// The `--dry-run=json` document: the resolved configuration, the exact
+/// argv that would be executed, and which layer supplied each value.
+#[derive(Serialize)]
+struct DryRunReport<'a> {
+    config: &'a Config,
+    argv: &'a [String],
+    sources: &'a BTreeMap<&'static str, Vec<Origin>>,
+    skipped_paths: &'a [SkippedPath],
+    symlink_targets: &'a [SymlinkTarget],
+    unsearched_dirs: &'a [secrets::Unsearched],
+}
+
+/// The `--stats=json` document: how the unit ended plus its counters.
+#[derive(Serialize)]
+struct StatsReport<'a> {
+    result: &'a str,
+    exit_code: i32,
+    #[serde(flatten)]
+    stats: &'a dbus::Stats,
+}
+
+/// Print the `--stats` report for a finished unit to stderr, or write it to
+/// `file`.
+fn report_stats(outcome: &dbus::Outcome, format: StatsFormat, file: Option<&Path>) -> Result<()> {
+    let report = match format {
+        StatsFormat::Text => outcome.stats.to_text(),
+        StatsFormat::Json => {
+            let report = StatsReport {
+                result: &outcome.result,
+                exit_code: outcome.exit_code(),
+                stats: &outcome.stats,
+            };
+            serde_json::to_string_pretty(&report)? + "\n"
+        }
+    };
+    match file {
+        Some(path) => std::fs::write(path, report)
+            .with_context(|| format!("failed to write stats to {}", path.display()))?,
+        None => eprint!("{}", report),
+    }
+    Ok(())
+}
+
 /// Quote an argument for safe display in `--dry-run` output. Anything outside
 /// a conservative set of shell-safe characters is single-quoted.
 fn shell_quote(s: &str) -> String {
@@ -573,32 +2720,361 @@ fn shell_quote(s: &str) -> String {
     }
 }
 
+// ============ Profiles Subcommand ============
+
+/// A profile's scalar settings as `(field, display value)` pairs, in the
+/// order `profiles show` and `profiles diff` print them. Unset is `-`.
+fn profile_settings(p: &Profile) -> Vec<(&'static str, String)> {
+    let p = &p.settings;
+    let show = |v: &Option<String>| v.clone().unwrap_or_else(|| "-".to_string());
+    vec![
+        ("memory_limit", show(&p.memory_limit)),
+        ("cpu_quota", show(&p.cpu_quota)),
+        ("memory_swap_max", show(&p.memory_swap_max)),
+        ("tasks_max", show(&p.tasks_max)),
+        ("allowed_cpus", show(&p.allowed_cpus)),
+        ("allowed_memory_nodes", show(&p.allowed_memory_nodes)),
+        ("cpu_weight", show(&p.cpu_weight)),
+        ("nice", show(&p.nice.map(|v| v.to_string()))),
+        (
+            "io_scheduling_class",
+            show(&p.io_scheduling_class.map(|v| v.to_string())),
+        ),
+        ("disk_read", show(&p.disk_read)),
+        ("disk_write", show(&p.disk_write)),
+        ("disk_read_iops", show(&p.disk_read_iops)),
+        ("disk_write_iops", show(&p.disk_write_iops)),
+        ("io_weight", show(&p.io_weight)),
+        ("timeout", show(&p.timeout)),
+        ("protect_home", show(&p.protect_home)),
+        ("protect_system", show(&p.protect_system)),
+        (
+            "private_network",
+            show(&p.private_network.map(|v| v.to_string())),
+        ),
+        ("project_root", show(&p.project_root)),
+        ("env_allow", show_list(&p.env_allow)),
+        ("env_deny", show_list(&p.env_deny)),
+        ("mask_secrets", show(&p.mask_secrets.map(|v| v.to_string()))),
+        ("secret_patterns", show_list(&p.secret_patterns)),
+        ("protect_git", show(&p.protect_git.map(|v| v.to_string()))),
+    ]
+}
+
+/// A list setting for `profile_settings`: space-separated, or `-` if empty.
+fn show_list(values: &[String]) -> String {
+    if values.is_empty() {
+        "-".to_string()
+    } else {
+        values.join(" ")
+    }
+}
+
+/// A profile's paths as `(field, expanded path)` pairs, in profile order.
+fn profile_paths(p: &Profile) -> Vec<(&'static str, String)> {
+    let rw = p
+        .settings
+        .rw_paths
+        .iter()
+        .map(|raw| ("rw_paths", expand_path(raw)));
+    let ro = p
+        .settings
+        .ro_paths
+        .iter()
+        .map(|raw| ("ro_paths", expand_path(raw)));
+    rw.chain(ro).collect()
+}
+
+// ============ Suggest Subcommand ============
+
+/// A profile recommended by `playpen suggest`, in the shape of a
+/// `profiles.toml` entry.
+#[derive(Serialize)]
+struct SuggestedProfile {
+    name: String,
+    description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    memory_limit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    memory_swap_max: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cpu_quota: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tasks_max: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    disk_read: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    disk_write: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SuggestedProfileFile {
+    profile: Vec<SuggestedProfile>,
+}
+
+/// Round a byte count up to a size that reads well in a profile: whole
+/// megabytes below 128M, 64M steps below 2G and 256M steps above, written
+/// in gigabytes when it is a whole number of them.
+fn round_up_size(bytes: f64) -> String {
+    let mib = (bytes / (1u64 << 20) as f64).ceil().max(1.0) as u64;
+    let step = match mib {
+        0..128 => 1,
+        128..2048 => 64,
+        _ => 256,
+    };
+    let mib = mib.div_ceil(step) * step;
+    if mib % 1024 == 0 {
+        format!("{}G", mib / 1024)
+    } else {
+        format!("{}M", mib)
+    }
+}
+
+/// Round a number of busy cores up to a `CPUQuota` in 50% steps, no more
+/// than the machine has.
+fn round_up_quota(cores: f64) -> String {
+    let host = std::thread::available_parallelism().map_or(1, |n| n.get()) as u64;
+    let percent = ((cores * 2.0).ceil().max(1.0) as u64 * 50).min(host * 100);
+    format!("{}%", percent)
+}
+
+/// Round a task count up to a multiple of 64, so a handful of extra
+/// threads on the next run does not hit the limit.
+fn round_up_tasks(tasks: f64) -> String {
+    let tasks = tasks.ceil().max(1.0) as u64;
+    (tasks.div_ceil(64) * 64).to_string()
+}
+
+/// Run the command in an unlimited unit with accounting on, then print a
+/// profile whose limits are the measured peaks times `headroom`. Returns
+/// the command's exit code.
+fn run_suggest_command(args: &SuggestArgs) -> Result<i32> {
+    if args.headroom.is_nan() || args.headroom < 1.0 {
+        bail!("--headroom must be at least 1.0, got {}", args.headroom);
+    }
+    let mut config = Config::defaults();
+    config.accounting = true;
+    let (env, command) =
+        environment::for_systemd(&environment::forwarded(&config), &args.command_and_args);
+    let outcome = dbus::run(&config, &env, &command, None)?;
+
+    // Peak rates need a second of samples; a shorter run falls back to its
+    // average, which for a short burst is close to the peak anyway.
+    let seconds = outcome.elapsed.as_secs_f64().max(f64::EPSILON);
+    let rate = |peak: Option<u64>, total: Option<u64>| {
+        peak.or_else(|| total.map(|t| (t as f64 / seconds) as u64))
+    };
+    let stats = &outcome.stats;
+    let cpu = rate(outcome.peaks.cpu_nsec_per_sec, stats.cpu_usage_nsec);
+    let read = rate(outcome.peaks.io_read_bytes_per_sec, stats.io_read_bytes);
+    let write = rate(outcome.peaks.io_write_bytes_per_sec, stats.io_write_bytes);
+
+    let name = args.name.clone().unwrap_or_else(|| {
+        Path::new(&args.command_and_args[0])
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| args.command_and_args[0].clone())
+    });
+    // A disk rate of zero means the command did no IO worth limiting.
+    let disk = |rate: Option<u64>| {
+        rate.filter(|&r| r > 0)
+            .map(|r| round_up_size(r as f64 * args.headroom))
+    };
+    let suggestion = SuggestedProfile {
+        name,
+        description: format!(
+            "Suggested by playpen suggest for `{}`",
+            args.command_and_args.join(" ")
+        ),
+        memory_limit: stats
+            .memory_peak_bytes
+            .map(|b| round_up_size(b as f64 * args.headroom)),
+        memory_swap_max: stats.memory_peak_bytes.map(|_| "0".to_string()),
+        cpu_quota: cpu.map(|ns| round_up_quota(ns as f64 / 1e9 * args.headroom)),
+        tasks_max: stats
+            .tasks_peak
+            .map(|n| round_up_tasks(n as f64 * args.headroom)),
+        disk_read: disk(read),
+        disk_write: disk(write),
+    };
+
+    let show = |v: Option<u64>, render: &dyn Fn(u64) -> String| {
+        v.map(render).unwrap_or_else(|| "n/a".to_string())
+    };
+    println!(
+        "# Measured over {:.1}s: memory peak {}, CPU peak {} cores, tasks peak {}, disk read peak {}/s, disk write peak {}/s",
+        seconds,
+        show(stats.memory_peak_bytes, &dbus::format_bytes),
+        show(cpu, &|ns| format!("{:.1}", ns as f64 / 1e9)),
+        show(stats.tasks_peak, &|n| n.to_string()),
+        show(read, &dbus::format_bytes),
+        show(write, &dbus::format_bytes),
+    );
+    println!(
+        "# Limits include {:.0}% headroom. Add protect_home and paths as needed.",
+        (args.headroom - 1.0) * 100.0
+    );
+    print!(
+        "{}",
+        toml::to_string(&SuggestedProfileFile {
+            profile: vec![suggestion],
+        })?
+    );
+
+    let code = outcome.exit_code();
+    if code != 0 {
+        eprintln!(
+            "playpen: warning: the command did not succeed ({}); the measurements may not reflect a full run",
+            outcome
+        );
+    }
+    Ok(code)
+}
+
+fn run_profiles_command(cmd: &ProfilesCommand, profiles: &[Profile]) {
+    match cmd {
+        ProfilesCommand::List => {
+            for p in profiles {
+                println!("{:12} - {}", p.name, p.description);
+            }
+        }
+        ProfilesCommand::Show { name } => {
+            let p = lookup_profile(profiles, name);
+            println!("{} - {}", p.name, p.description);
+            for (field, value) in profile_settings(p) {
+                println!("  {:20} {}", field, value);
+            }
+            // Paths are shown expanded; the ones `push_if_exists` would drop
+            // on this machine are marked rather than hidden.
+            for (field, path) in profile_paths(p) {
+                if Path::new(&path).exists() {
+                    println!("  {:20} {}", field, path);
+                } else {
+                    println!("  {:20} {} (skipped: does not exist)", field, path);
+                }
+            }
+        }
+        ProfilesCommand::Diff { a, b } => {
+            let (pa, pb) = (lookup_profile(profiles, a), lookup_profile(profiles, b));
+            println!("--- {}", pa.name);
+            println!("+++ {}", pb.name);
+            let mut identical = true;
+            for ((field, va), (_, vb)) in profile_settings(pa).into_iter().zip(profile_settings(pb))
+            {
+                if va != vb {
+                    identical = false;
+                    println!("- {:20} {}", field, va);
+                    println!("+ {:20} {}", field, vb);
+                }
+            }
+            let (paths_a, paths_b) = (profile_paths(pa), profile_paths(pb));
+            for entry in paths_a.iter().filter(|e| !paths_b.contains(e)) {
+                identical = false;
+                println!("- {:20} {}", entry.0, entry.1);
+            }
+            for entry in paths_b.iter().filter(|e| !paths_a.contains(e)) {
+                identical = false;
+                println!("+ {:20} {}", entry.0, entry.1);
+            }
+            if identical {
+                println!("(no differences)");
+            }
+        }
+    }
+}
+
+// ============ Trust Subcommand ============
+
+/// Approve `path`, or the project file governing the working directory, as
+/// it is now.
+fn run_trust_command(path: Option<&Path>) -> Result<()> {
+    let path = match path {
+        Some(path) => std::fs::canonicalize(path)
+            .with_context(|| format!("failed to read {}", path.display()))?,
+        None => {
+            let cwd = std::env::current_dir().context("failed to read the current directory")?;
+            find_project_file(&cwd).ok_or_else(|| {
+                anyhow!(
+                    "no {} in the current directory or its parents",
+                    PROJECT_FILE_NAME
+                )
+            })?
+        }
+    };
+    let text = std::fs::read_to_string(&path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    // A file playpen would refuse to apply is not worth approving.
+    parse_project_file(path.clone(), &text)?;
+    let dir = config_dir().ok_or_else(|| anyhow!("cannot locate the configuration directory"))?;
+    trust::trust(&trust::store_path(&dir), &path, &text)?;
+    println!("Trusted {}", path.display());
+    Ok(())
+}
+
 // ============ Main ============
 
+/// Pick how to launch the unit. Without `--backend`, anything that needs
+/// playpen to watch the unit selects the D-Bus backend: `--stats` and
+/// `--idle-timeout` cannot work otherwise, and a timeout only gets its own
+/// exit status there. `systemd-run` exits as soon as the unit stops, taking
+/// the counters and the reason with it.
+fn choose_backend(cli: &Run, config: &Config) -> Result<Backend> {
+    let watched = [
+        ("--stats", cli.stats.is_some()),
+        ("--idle-timeout", cli.idle_timeout.is_some()),
+    ];
+    match cli.backend {
+        Some(Backend::SystemdRun) => {
+            if let Some((flag, _)) = watched.iter().find(|(_, set)| *set) {
+                bail!(
+                    "{} needs --backend dbus; systemd-run does not let playpen watch the unit",
+                    flag
+                );
+            }
+            Ok(Backend::SystemdRun)
+        }
+        Some(backend) => Ok(backend),
+        None if watched.iter().any(|(_, set)| *set) || config.runtime_max.is_some() => {
+            Ok(Backend::Dbus)
+        }
+        None => Ok(Backend::SystemdRun),
+    }
+}
+
 fn main() -> Result<()> {
-    let cli = Run::parse();
+    let cli = Cli::parse();
+    match &cli.command {
+        Some(Command::Profiles(cmd)) => {
+            run_profiles_command(cmd, &load_profiles()?);
+            return Ok(());
+        }
+        Some(Command::Suggest(args)) => std::process::exit(run_suggest_command(args)?),
+        Some(Command::Trust { path }) => return run_trust_command(path.as_deref()),
+        None => {}
+    }
+    let cli = cli.run;
+
+    let profiles = load_profiles()?;
+    let project = if cli.no_project_file {
+        None
+    } else {
+        load_project_file()?
+    };
+    let config = Config::resolve(&cli, &profiles, project.as_ref())?;
+    let backend = choose_backend(&cli, &config)?;
 
     let mut parts = vec!["systemd-run".to_string()];
     let base_command = "--user --same-dir --wait --pipe";
     parts.extend(base_command.split_whitespace().map(String::from));
 
-    // Include all env vars in the calling environment
-    if cli.capture_env {
-        for (key, value) in std::env::vars() {
-            // Skip the environment variables that systemd-run sets
-            if key == "DBUS_SESSION_BUS_ADDRESS" {
-                continue;
-            }
-            // Skip env vars that are actually exported bash functions
-            if key.starts_with("BASH_FUNC_") && key.ends_with("%%") {
-                continue;
-            }
-            parts.push(format!(r#"--setenv={}="{}""#, key, value));
-        }
-    } else if cli.capture_path {
-        if let Some(path) = std::env::var_os("PATH") {
-            parts.push(format!(r#"--setenv=PATH="{}""#, path.to_string_lossy()));
-        }
+    // systemd-run is exec'd directly, so UTF-8 values reach it as --setenv
+    // arguments exactly as they are. Any other value can't travel that way;
+    // the command is then wrapped in `/bin/sh -c`, which rebuilds the value
+    // from octal escapes with printf before exec'ing the command.
+    let (env, command) =
+        environment::for_systemd(&environment::forwarded(&config), &cli.command_and_args);
+    for assignment in &env {
+        parts.push(format!("--setenv={}", assignment));
     }
 
     // Only add --pty if we are attached to a terminal
@@ -610,15 +3086,55 @@ fn main() -> Result<()> {
         parts.push("--quiet".to_string());
     }
 
-    parts.extend(Config::resolve(&cli).to_systemd_args());
-    parts.extend(cli.command_and_args.clone());
+    parts.extend(config.to_systemd_args());
+    parts.extend(command.iter().cloned());
 
-    if cli.dry_run {
-        let rendered: Vec<String> = parts.iter().map(|s| shell_quote(s)).collect();
-        println!("{}", rendered.join(" "));
+    if cli.explain {
+        print!("{}", config.explain());
         return Ok(());
     }
 
+    match cli.dry_run {
+        Some(DryRunFormat::Text) => {
+            let rendered: Vec<String> = parts.iter().map(|s| shell_quote(s)).collect();
+            println!("{}", rendered.join(" "));
+            return Ok(());
+        }
+        Some(DryRunFormat::Json) => {
+            let report = DryRunReport {
+                config: &config,
+                argv: &parts,
+                sources: &config.origins,
+                skipped_paths: &config.skipped_paths,
+                symlink_targets: &config.symlink_targets,
+                unsearched_dirs: &config.unsearched_dirs,
+            };
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            return Ok(());
+        }
+        None => {}
+    }
+
+    if backend == Backend::Dbus {
+        match dbus::run(&config, &env, &command, cli.idle_timeout) {
+            Ok(outcome) => {
+                if !cli.quiet {
+                    eprintln!("playpen: {}", outcome);
+                }
+                if let Some(format) = cli.stats {
+                    if let Err(err) = report_stats(&outcome, format, cli.stats_file.as_deref()) {
+                        eprintln!("Error: {:?}", err);
+                    }
+                }
+                std::process::exit(outcome.exit_code());
+            }
+            Err(err) => {
+                eprintln!("Error: {:?}", err);
+                std::process::exit(dbus::EXIT_SANDBOX_FAILURE);
+            }
+        }
+    }
+
     let execvp_args: Vec<CString> = parts
         .iter()
         .map(|s| CString::new(s.clone()).unwrap())
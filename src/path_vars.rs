@@ -0,0 +1,63 @@
+//! Variables that paths in profiles, project files and path flags can use
+//! whether or not the calling environment exports them.
+//!
+//! `$UID` is a shell variable that is rarely exported, the XDG directories
+//! are often left unset in favour of their defaults, and `$PROJECT_ROOT` and
+//! `$CWD` are not environment variables at all. Each is computed here, so a
+//! profile path such as `/run/user/$UID` means the same thing everywhere.
+//! Any other name is looked up in the environment.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// The project directory the run resolved to, once it is known.
+static PROJECT_ROOT: OnceLock<PathBuf> = OnceLock::new();
+
+/// Make `$PROJECT_ROOT` the project directory `dir` from here on.
+pub(crate) fn set_project_root(dir: &Path) {
+    let _ = PROJECT_ROOT.set(dir.to_path_buf());
+}
+
+/// The value of the path variable `name`, if it has one.
+pub(crate) fn lookup(name: &str) -> Option<String> {
+    let uid = || nix::unistd::getuid().to_string();
+    let cwd = || std::env::current_dir().ok();
+    let value = match name {
+        "UID" => uid(),
+        "XDG_RUNTIME_DIR" => xdg_dir(name, || Some(format!("/run/user/{}", uid())))?,
+        "XDG_CACHE_HOME" => xdg_dir(name, || home_join(".cache"))?,
+        "XDG_CONFIG_HOME" => xdg_dir(name, || home_join(".config"))?,
+        "XDG_DATA_HOME" => xdg_dir(name, || home_join(".local/share"))?,
+        "XDG_STATE_HOME" => xdg_dir(name, || home_join(".local/state"))?,
+        // Before a run settles its project directory, as for `profiles
+        // show`, the enclosing checkout stands in for it.
+        "PROJECT_ROOT" => match PROJECT_ROOT.get() {
+            Some(dir) => dir.display().to_string(),
+            None => {
+                let cwd = cwd()?;
+                crate::project::git_root(&cwd)
+                    .unwrap_or(cwd)
+                    .display()
+                    .to_string()
+            }
+        },
+        "CWD" => cwd()?.display().to_string(),
+        _ => std::env::var(name).ok()?,
+    };
+    Some(value)
+}
+
+/// An XDG base directory: the environment's value if it is an absolute
+/// path, as the spec requires, otherwise the spec's default.
+fn xdg_dir(name: &str, default: impl FnOnce() -> Option<String>) -> Option<String> {
+    std::env::var(name)
+        .ok()
+        .filter(|v| v.starts_with('/'))
+        .or_else(default)
+}
+
+/// `rest` under the home directory.
+fn home_join(rest: &str) -> Option<String> {
+    let home = std::env::var("HOME").ok().filter(|h| !h.is_empty())?;
+    Some(Path::new(&home).join(rest).display().to_string())
+}
@@ -0,0 +1,143 @@
+//! Finding the directory a command's project lives in, for
+//! `--project-root`, and the git metadata a checkout keeps elsewhere.
+
+use anyhow::{bail, Result};
+use std::path::{Path, PathBuf};
+
+/// The `--project-root` modes; any other value is a directory.
+pub(crate) const MODES: &[&str] = &["auto", "git", "cwd"];
+
+/// The top of the git checkout containing `start`: the nearest directory
+/// with a `.git` entry, which is a file in a worktree or submodule.
+pub(crate) fn git_root(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+/// The directory `mode` names for a command run in `cwd`. `auto` is the
+/// git root unless that is the home directory, which a dotfiles repository
+/// would otherwise expose in full, and the working directory outside a
+/// checkout; `git` insists on a checkout.
+pub(crate) fn locate(mode: &str, cwd: &Path) -> Result<PathBuf> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    Ok(match mode {
+        "cwd" => cwd.to_path_buf(),
+        "auto" => git_root(cwd)
+            .filter(|root| Some(root) != home.as_ref() && root.parent().is_some())
+            .unwrap_or_else(|| cwd.to_path_buf()),
+        "git" => match git_root(cwd) {
+            Some(root) => root,
+            None => bail!(
+                "--project-root git: {} is not inside a git repository",
+                cwd.display()
+            ),
+        },
+        dir => PathBuf::from(dir),
+    })
+}
+
+/// Git directories a checkout at `root` uses outside itself. A worktree's
+/// or submodule's `.git` is a file pointing at its real git directory, and
+/// a worktree's git directory shares objects and refs with the main
+/// repository's (its `commondir`). Empty for an ordinary checkout.
+pub(crate) fn linked_git_dirs(root: &Path) -> Vec<PathBuf> {
+    match git_file_dirs(root) {
+        Some((git_dir, Some(common))) if git_dir.starts_with(&common) => vec![common],
+        Some((git_dir, Some(common))) => vec![git_dir, common],
+        Some((git_dir, None)) => vec![git_dir],
+        None => Vec::new(),
+    }
+}
+
+/// The git directory named by a `.git` file at `root`, and the common
+/// directory it shares with the main repository, if any.
+fn git_file_dirs(root: &Path) -> Option<(PathBuf, Option<PathBuf>)> {
+    let text = std::fs::read_to_string(root.join(".git")).ok()?;
+    let git_dir = text.trim().strip_prefix("gitdir:")?;
+    let git_dir = std::fs::canonicalize(root.join(git_dir.trim())).ok()?;
+    let common = std::fs::read_to_string(git_dir.join("commondir"))
+        .ok()
+        .and_then(|c| std::fs::canonicalize(git_dir.join(c.trim())).ok());
+    Some((git_dir, common))
+}
+
+/// Entries of a git directory that decide what git runs and how: hooks,
+/// configuration (which can also name hook paths, filters, pagers and
+/// editors), the repository's own attributes and excludes, and the links
+/// between a worktree's git directory and the main one.
+const PROTECTED_GIT_ENTRIES: &[&str] = &[
+    "hooks",
+    "config",
+    "config.worktree",
+    "info",
+    "commondir",
+    "gitdir",
+];
+
+/// The git directories of the checkout at `root`: its `.git` directory or,
+/// in a worktree or submodule, the directories its `.git` file points at,
+/// and the git directories of its submodules under their `modules`.
+/// `--protect-git` binds each onto itself, which stops the command from
+/// moving it aside and putting a git directory of its own in its place.
+pub(crate) fn git_dirs(root: &Path) -> Vec<PathBuf> {
+    let own: Vec<PathBuf> = match git_file_dirs(root) {
+        Some((git_dir, common)) => std::iter::once(git_dir).chain(common).collect(),
+        None => Some(root.join(".git"))
+            .filter(|dir| dir.is_dir())
+            .into_iter()
+            .collect(),
+    };
+    let mut dirs = Vec::new();
+    for dir in own {
+        let modules = dir.join("modules");
+        dirs.push(dir);
+        module_git_dirs(&modules, &mut dirs);
+    }
+    dirs
+}
+
+/// Add the submodule git directories under `modules` to `dirs`, with those
+/// of their own submodules. A submodule named `a/b` lives at `modules/a/b`,
+/// so a directory without a `HEAD` is searched further.
+fn module_git_dirs(modules: &Path, dirs: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(modules) else {
+        return;
+    };
+    let mut entries: Vec<_> = entries.flatten().collect();
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        if !entry.file_type().is_ok_and(|t| t.is_dir()) {
+            continue;
+        }
+        let dir = entry.path();
+        if dir.join("HEAD").is_file() {
+            let modules = dir.join("modules");
+            dirs.push(dir);
+            module_git_dirs(&modules, dirs);
+        } else {
+            module_git_dirs(&dir, dirs);
+        }
+    }
+}
+
+/// What `--protect-git` makes read-only for the checkout at `root`: the
+/// protected entries each of its git directories has and, in a worktree or
+/// submodule, the `.git` file that points at them. A missing entry cannot
+/// be bound, so it is left out.
+pub(crate) fn protected_git_paths(root: &Path) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if git_file_dirs(root).is_some() {
+        paths.push(root.join(".git"));
+    }
+    for dir in git_dirs(root) {
+        for entry in PROTECTED_GIT_ENTRIES {
+            let path = dir.join(entry);
+            if path.symlink_metadata().is_ok() {
+                paths.push(path);
+            }
+        }
+    }
+    paths
+}
@@ -0,0 +1,109 @@
+//! Finding secret-looking files in the project directory for
+//! `--mask-secrets`, which makes them inaccessible in the sandbox.
+
+use crate::environment::matches;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// File names masked by default: environment files, private keys and
+/// certificates, and tool configs that usually hold tokens.
+pub(crate) const DEFAULT_PATTERNS: &[&str] = &[
+    ".env",
+    ".env.*",
+    "*.pem",
+    "*.key",
+    "*.p12",
+    "*.pfx",
+    "*.jks",
+    "*.keystore",
+    "id_rsa",
+    "id_ecdsa",
+    "id_ed25519",
+    "id_dsa",
+    ".npmrc",
+    ".pypirc",
+    ".netrc",
+    ".git-credentials",
+    ".htpasswd",
+    "credentials.json",
+];
+
+/// Directories not searched: version control internals, dependency trees
+/// and build output, which are large and hold other people's fixtures
+/// rather than the project's secrets.
+const SKIPPED_DIRS: &[&str] = &[
+    ".git",
+    "node_modules",
+    "target",
+    ".venv",
+    "venv",
+    "__pycache__",
+];
+
+/// How many directory levels below the root are searched.
+pub(crate) const MAX_DEPTH: usize = 6;
+
+/// A directory the search did not look inside, so any secrets in it stay
+/// visible in the sandbox.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Unsearched {
+    pub(crate) path: PathBuf,
+    pub(crate) reason: Reason,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Reason {
+    /// It is one of `SKIPPED_DIRS`.
+    Skipped,
+    /// It is `MAX_DEPTH` levels below the root.
+    TooDeep,
+}
+
+/// The outcome of a search: the matching files and the directories left
+/// out.
+#[derive(Default)]
+pub(crate) struct Found {
+    pub(crate) files: Vec<PathBuf>,
+    pub(crate) unsearched: Vec<Unsearched>,
+}
+
+/// Every file under `root` whose name matches one of `patterns`, in a
+/// stable order. Symlinked directories are not followed, so the search
+/// stays inside the project.
+pub(crate) fn find(root: &Path, patterns: &[String]) -> Found {
+    let mut found = Found::default();
+    walk(root, patterns, 0, &mut found);
+    found
+}
+
+fn walk(dir: &Path, patterns: &[String], depth: usize, found: &mut Found) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<_> = entries.flatten().collect();
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if file_type.is_dir() {
+            let reason = if SKIPPED_DIRS.contains(&name.as_ref()) {
+                Reason::Skipped
+            } else if depth >= MAX_DEPTH {
+                Reason::TooDeep
+            } else {
+                walk(&entry.path(), patterns, depth + 1, found);
+                continue;
+            };
+            found.unsearched.push(Unsearched {
+                path: entry.path(),
+                reason,
+            });
+        } else if patterns.iter().any(|p| matches(p, &name)) {
+            found.files.push(entry.path());
+        }
+    }
+}
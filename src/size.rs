@@ -0,0 +1,133 @@
+//! Sizes and rates as written on the command line, in profiles and in
+//! project files: `512M`, `2G`, a percentage, a share of physical memory
+//! (`25%ram`) or `infinity`.
+//!
+//! Every memory and disk value goes through here before it reaches systemd,
+//! so a typo such as `2GB` is reported by playpen, naming the value, rather
+//! than as an unclear failure from inside `systemd-run`.
+
+use anyhow::{anyhow, bail, Context, Result};
+
+/// A memory value, parsed but not yet resolved.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Size {
+    Bytes(u64),
+    /// `N%`, passed on to systemd, which reads it as a share of physical
+    /// memory. (`--memory-high`/`--memory-low` take it as a share of the
+    /// memory limit instead; see `share_of_limit` in main.rs.)
+    Percent(f64),
+    /// `N%ram`: a share of physical memory that playpen resolves itself.
+    PercentOfRam(f64),
+    Infinity,
+}
+
+impl Size {
+    pub(crate) fn parse(value: &str) -> Result<Size> {
+        if let Some(pct) = value.strip_suffix("%ram") {
+            return percent(value, pct).map(Size::PercentOfRam);
+        }
+        if let Some(pct) = value.strip_suffix('%') {
+            return percent(value, pct).map(Size::Percent);
+        }
+        match parse_bytes(value)? {
+            u64::MAX => Ok(Size::Infinity),
+            bytes => Ok(Size::Bytes(bytes)),
+        }
+    }
+}
+
+/// The number in front of a `%` or `%ram`, which must be from 0 to 100.
+fn percent(value: &str, pct: &str) -> Result<f64> {
+    match pct.parse::<f64>() {
+        Ok(n) if (0.0..=100.0).contains(&n) => Ok(n),
+        _ => bail!("invalid percentage: {} (expected 0% to 100%)", value),
+    }
+}
+
+/// A byte count with an optional base-1024 suffix (`K`, `M`, `G`, `T`, `P`,
+/// `E`), as systemd writes memory sizes, or `infinity` as `u64::MAX`.
+pub(crate) fn parse_bytes(value: &str) -> Result<u64> {
+    parse_quantity(value, "size")
+}
+
+/// A disk bandwidth in bytes per second, written like a size (`50M` is
+/// 50 MB/s). Percentages make no sense for a rate.
+pub(crate) fn parse_rate(value: &str) -> Result<u64> {
+    parse_quantity(value, "rate")
+}
+
+/// `parse_bytes`, naming the value a `kind` in errors.
+fn parse_quantity(value: &str, kind: &str) -> Result<u64> {
+    if value == "infinity" {
+        return Ok(u64::MAX);
+    }
+    let invalid = || {
+        anyhow!(
+            "invalid {}: {} (expected a number with an optional K, M, G or T suffix, e.g. 512M)",
+            kind,
+            value
+        )
+    };
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, suffix) = value.split_at(split);
+    let multiplier: u64 = match suffix {
+        "" | "B" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        "P" => 1 << 50,
+        "E" => 1 << 60,
+        // `2GB`, `2GiB`: the suffix letter alone already means bytes.
+        _ if !number.is_empty() && suffix.len() > 1 && suffix.ends_with('B') => bail!(
+            "invalid {}: {} (did you mean {}{}?)",
+            kind,
+            value,
+            number,
+            &suffix[..1]
+        ),
+        _ => return Err(invalid()),
+    };
+    let number: f64 = number.parse().map_err(|_| invalid())?;
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// Physical memory, from `MemTotal` in `/proc/meminfo`.
+pub(crate) fn total_ram() -> Result<u64> {
+    let meminfo =
+        std::fs::read_to_string("/proc/meminfo").context("failed to read /proc/meminfo")?;
+    meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix("MemTotal:"))
+        .and_then(|rest| rest.trim().strip_suffix("kB"))
+        .and_then(|kb| kb.trim().parse::<u64>().ok())
+        .map(|kb| kb << 10)
+        .ok_or_else(|| anyhow!("no MemTotal in /proc/meminfo"))
+}
+
+/// Resolve `N%ram` to bytes given the machine's memory, or to the plain
+/// `N%` systemd would read the same way if the memory size is unknown.
+/// Any other value is returned unchanged.
+pub(crate) fn resolve_ram_share(value: &str, ram: Option<u64>) -> String {
+    match (Size::parse(value), ram) {
+        (Ok(Size::PercentOfRam(pct)), Some(ram)) => format_size((ram as f64 * pct / 100.0) as u64),
+        (Ok(Size::PercentOfRam(pct)), None) => format!("{}%", pct),
+        _ => value.to_string(),
+    }
+}
+
+/// Render a byte count for a unit property, rounded down to a whole
+/// kilobyte and written with the largest suffix that keeps it exact.
+pub(crate) fn format_size(bytes: u64) -> String {
+    let kib = bytes >> 10;
+    if kib == 0 {
+        return bytes.to_string();
+    }
+    match kib {
+        k if k % (1 << 20) == 0 => format!("{}G", k >> 20),
+        k if k % (1 << 10) == 0 => format!("{}M", k >> 10),
+        k => format!("{}K", k),
+    }
+}
@@ -0,0 +1,60 @@
+//! The record of `.playpen.toml` files the user has approved with
+//! `playpen trust`. A project file can loosen the sandbox, and whatever ran
+//! in the sandbox last time may have rewritten it, so playpen only applies
+//! a file whose exact contents were approved.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// The on-disk shape of the record: one `[[file]]` table per approved
+/// project file, holding the contents it had when it was approved.
+#[derive(Default, Serialize, Deserialize)]
+struct Store {
+    #[serde(default)]
+    file: Vec<Entry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    path: PathBuf,
+    contents: String,
+}
+
+/// Where the record lives under the user's configuration directory.
+pub(crate) fn store_path(config_dir: &Path) -> PathBuf {
+    config_dir.join("playpen/trusted.toml")
+}
+
+fn load(store: &Path) -> Result<Store> {
+    let text = match std::fs::read_to_string(store) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Store::default()),
+        Err(e) => return Err(e).with_context(|| format!("failed to read {}", store.display())),
+    };
+    toml::from_str(&text).with_context(|| format!("failed to parse {}", store.display()))
+}
+
+/// Whether `contents` is what `file` held when it was last approved.
+pub(crate) fn is_trusted(store: &Path, file: &Path, contents: &str) -> Result<bool> {
+    Ok(load(store)?
+        .file
+        .iter()
+        .any(|e| e.path == file && e.contents == contents))
+}
+
+/// Approve `file` as it is now, replacing any earlier approval of it.
+pub(crate) fn trust(store: &Path, file: &Path, contents: &str) -> Result<()> {
+    let mut record = load(store)?;
+    record.file.retain(|e| e.path != file);
+    record.file.push(Entry {
+        path: file.to_path_buf(),
+        contents: contents.to_string(),
+    });
+    if let Some(dir) = store.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+    let text = toml::to_string(&record).expect("trust record serializes");
+    std::fs::write(store, text).with_context(|| format!("failed to write {}", store.display()))
+}
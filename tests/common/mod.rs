@@ -4,7 +4,7 @@
 #![allow(dead_code)]
 
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use tempfile::TempDir;
 
@@ -53,3 +53,54 @@ pub fn node_available() -> bool {
         .map(|output| output.status.success())
         .unwrap_or(false)
 }
+
+/// Create a temporary `XDG_CONFIG_HOME` whose `playpen/profiles.toml` holds
+/// `contents`. Point playpen at it with `.env("XDG_CONFIG_HOME", dir.path())`.
+pub fn create_config_dir(contents: &str) -> TempDir {
+    let dir = create_temp_dir();
+    std::fs::create_dir_all(dir.path().join("playpen")).unwrap();
+    std::fs::write(dir.path().join("playpen/profiles.toml"), contents).unwrap();
+    dir
+}
+
+/// Escape `s` for use inside a regular expression.
+pub fn regex_escape(s: &str) -> String {
+    s.chars()
+        .flat_map(|c| {
+            if c.is_ascii_alphanumeric() || c == '/' || c == '_' || c == '-' {
+                vec![c]
+            } else {
+                vec!['\\', c]
+            }
+        })
+        .collect()
+}
+
+/// Check if dbus-daemon is available on the system
+pub fn dbus_daemon_available() -> bool {
+    Command::new("dbus-daemon")
+        .arg("--version")
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+/// Approve the `.playpen.toml` governing `dir` with `playpen trust`, in a
+/// fresh `XDG_CONFIG_HOME`. Point playpen at it with
+/// `.env("XDG_CONFIG_HOME", config.path())`.
+pub fn trust_project_file(dir: &Path) -> TempDir {
+    let config = create_temp_dir();
+    let status = Command::new(get_playpen_path())
+        .arg("trust")
+        .current_dir(dir)
+        .env("XDG_CONFIG_HOME", config.path())
+        .stdout(std::process::Stdio::null())
+        .status()
+        .expect("Failed to run playpen trust");
+    assert!(
+        status.success(),
+        "playpen trust failed in {}",
+        dir.display()
+    );
+    config
+}
@@ -0,0 +1,147 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+mod common;
+
+/// The CPU count playpen sees, which scales `cores` and `%host` limits.
+fn host_cpus() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+#[test]
+fn test_cpu_limit_percentage_unchanged() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["-c", "300%", "--dry-run", "--", "cargo", "build"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pCPUQuota=300%"));
+}
+
+#[test]
+fn test_cpu_limit_in_cores() {
+    for (value, quota) in [("3cores", "300%"), ("1core", "100%"), ("0.5cores", "50%")] {
+        let mut cmd = Command::new(common::get_playpen_path());
+        cmd.args(["--cpu-limit", value, "--dry-run", "--", "cargo", "build"]);
+
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains(format!("-pCPUQuota={}", quota)));
+    }
+}
+
+#[test]
+fn test_cpu_limit_share_of_host() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--cpu-limit",
+        "50%host",
+        "--dry-run",
+        "--",
+        "cargo",
+        "build",
+    ]);
+
+    let expected = format!("-pCPUQuota={}%", (host_cpus() * 50).max(1));
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(expected));
+}
+
+#[test]
+fn test_whole_host() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--cpu-limit",
+        "100%host",
+        "--dry-run",
+        "--",
+        "cargo",
+        "build",
+    ]);
+
+    let expected = format!("-pCPUQuota={}%", host_cpus() * 100);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(expected));
+}
+
+#[test]
+fn test_invalid_cpu_limit_rejected() {
+    for value in ["lots", "0cores", "50%machine", "%host", "infcores"] {
+        let mut cmd = Command::new(common::get_playpen_path());
+        cmd.args(["--cpu-limit", value, "--dry-run", "--", "cargo", "build"]);
+
+        cmd.assert()
+            .failure()
+            .stderr(predicate::str::contains("invalid CPU limit"));
+    }
+}
+
+#[test]
+fn test_profile_half_the_machine() {
+    let config = common::create_config_dir(
+        "[[profile]]\nname = \"half\"\nextends = \"cargo\"\ncpu_quota = \"50%host\"\n",
+    );
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args(["--profile", "half", "--dry-run", "--", "cargo", "build"]);
+
+    let expected = format!("-pCPUQuota={}%", (host_cpus() * 50).max(1));
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(expected))
+        .stdout(predicate::str::contains("-pCPUQuota=300%").not());
+}
+
+#[test]
+fn test_profile_invalid_cpu_quota_rejected() {
+    let config = common::create_config_dir("[[profile]]\nname = \"half\"\ncpu_quota = \"half\"\n");
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args(["--profile", "half", "--dry-run", "--", "cargo", "build"]);
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("bad cpu_quota"));
+}
+
+#[test]
+fn test_project_file_cpu_in_cores() {
+    let project = common::create_temp_dir();
+    std::fs::write(
+        project.path().join(".playpen.toml"),
+        "cpu_quota = \"2cores\"\n",
+    )
+    .unwrap();
+    let config = common::trust_project_file(project.path());
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.current_dir(project.path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args(["--dry-run", "--", "make"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pCPUQuota=200%"));
+}
+
+#[test]
+fn test_explain_shows_given_cpu_limit() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--cpu-limit",
+        "3cores",
+        "--dry-run=json",
+        "--",
+        "cargo",
+        "build",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\"cpu_quota\": \"300%\""))
+        .stdout(predicate::str::contains("\"value\": \"3cores\""));
+}
@@ -0,0 +1,874 @@
+//! `--backend dbus` against a private bus with a mock systemd user manager,
+//! so the tests need neither a running systemd nor permission to create
+//! real units.
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::process::{Child, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+use zbus::blocking::connection::Builder;
+use zbus::blocking::Connection;
+use zbus::zvariant::{Fd, OwnedObjectPath, OwnedValue};
+
+mod common;
+
+const UNIT_PATH: &str = "/org/freedesktop/systemd1/unit/playpen_2dtest_2eservice";
+
+/// Unit properties recorded by the mock manager, keyed by name, rendered
+/// with `Debug` so tests can match on them.
+type Recorded = Arc<Mutex<BTreeMap<String, String>>>;
+
+struct Manager {
+    recorded: Recorded,
+    started: Arc<Mutex<Vec<String>>>,
+    /// Whether the unit is running; see `Unit`.
+    active: Arc<AtomicBool>,
+    /// Written to the unit's stdout when it starts.
+    output: Option<&'static str>,
+    /// Run the unit's `ExecStart=` with its `Environment=` and stdout, the
+    /// way systemd would, before returning.
+    execute: bool,
+}
+
+#[zbus::interface(name = "org.freedesktop.systemd1.Manager")]
+impl Manager {
+    fn subscribe(&self) {}
+
+    fn start_transient_unit(
+        &self,
+        name: String,
+        _mode: String,
+        properties: Vec<(String, OwnedValue)>,
+        _aux: Vec<(String, Vec<(String, OwnedValue)>)>,
+    ) -> OwnedObjectPath {
+        if self.execute {
+            execute(&properties);
+        }
+        let mut recorded = self.recorded.lock().unwrap();
+        for (key, value) in properties {
+            if let (Some(output), "StandardOutputFileDescriptor") = (self.output, key.as_str()) {
+                let fd = Fd::try_from(value.try_clone().unwrap()).unwrap();
+                let mut stdout = std::fs::File::from(std::os::fd::OwnedFd::try_from(fd).unwrap());
+                stdout.write_all(output.as_bytes()).unwrap();
+            }
+            let entry = recorded.entry(key).or_default();
+            entry.push_str(&format!("{:?}", &*value));
+        }
+        self.started.lock().unwrap().push(name);
+        OwnedObjectPath::try_from("/org/freedesktop/systemd1/job/1").unwrap()
+    }
+
+    fn get_unit(&self, _name: String) -> OwnedObjectPath {
+        OwnedObjectPath::try_from(UNIT_PATH).unwrap()
+    }
+
+    async fn stop_unit(
+        &self,
+        _name: String,
+        _mode: String,
+        #[zbus(object_server)] server: &zbus::ObjectServer,
+    ) -> OwnedObjectPath {
+        self.active.store(false, Ordering::Relaxed);
+        let unit = server.interface::<_, Unit>(UNIT_PATH).await.unwrap();
+        unit.get()
+            .await
+            .active_state_changed(unit.signal_emitter())
+            .await
+            .unwrap();
+        OwnedObjectPath::try_from("/org/freedesktop/systemd1/job/2").unwrap()
+    }
+}
+
+/// Run the command a transient unit describes, with only its environment,
+/// writing to its stdout.
+fn execute(properties: &[(String, OwnedValue)]) {
+    let property = |name: &str| {
+        properties
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.try_clone().unwrap())
+            .unwrap()
+    };
+    let exec: Vec<(String, Vec<String>, bool)> = property("ExecStart").try_into().unwrap();
+    let environment: Vec<String> = property("Environment").try_into().unwrap();
+    let stdout = Fd::try_from(property("StandardOutputFileDescriptor")).unwrap();
+    let stdout = std::os::fd::OwnedFd::try_from(stdout).unwrap();
+
+    let (path, argv, _) = &exec[0];
+    std::process::Command::new(path)
+        .args(&argv[1..])
+        .env_clear()
+        .envs(environment.iter().filter_map(|a| a.split_once('=')))
+        .stdout(stdout)
+        .status()
+        .unwrap();
+}
+
+/// A unit that is either still running until playpen stops it, or has
+/// already stopped by the time playpen looks at it.
+struct Unit {
+    active: Arc<AtomicBool>,
+}
+
+#[zbus::interface(name = "org.freedesktop.systemd1.Unit")]
+impl Unit {
+    #[zbus(property)]
+    fn active_state(&self) -> String {
+        if self.active.load(Ordering::Relaxed) {
+            "active".into()
+        } else {
+            "failed".into()
+        }
+    }
+
+    #[zbus(property)]
+    fn job(&self) -> (u32, OwnedObjectPath) {
+        (0, OwnedObjectPath::try_from("/").unwrap())
+    }
+}
+
+struct Service {
+    result: &'static str,
+    code: i32,
+    status: i32,
+    memory_peak: u64,
+}
+
+#[zbus::interface(name = "org.freedesktop.systemd1.Service")]
+impl Service {
+    #[zbus(property)]
+    fn result(&self) -> String {
+        self.result.into()
+    }
+
+    #[zbus(property)]
+    fn exec_main_code(&self) -> i32 {
+        self.code
+    }
+
+    #[zbus(property)]
+    fn exec_main_status(&self) -> i32 {
+        self.status
+    }
+
+    #[zbus(property)]
+    fn memory_peak(&self) -> u64 {
+        self.memory_peak
+    }
+
+    #[zbus(property, name = "CPUUsageNSec")]
+    fn cpu_usage_nsec(&self) -> u64 {
+        1_500_000_000
+    }
+
+    #[zbus(property, name = "IOReadBytes")]
+    fn io_read_bytes(&self) -> u64 {
+        10 << 20
+    }
+
+    #[zbus(property, name = "IOWriteBytes")]
+    fn io_write_bytes(&self) -> u64 {
+        u64::MAX
+    }
+
+    #[zbus(property)]
+    fn tasks_current(&self) -> u64 {
+        7
+    }
+}
+
+/// A private dbus-daemon with the mock manager registered on it. Dropping it
+/// stops the daemon.
+struct MockBus {
+    daemon: Child,
+    address: String,
+    recorded: Recorded,
+    started: Arc<Mutex<Vec<String>>>,
+    _conn: Connection,
+    _dir: TempDir,
+}
+
+impl Drop for MockBus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}
+
+fn start_mock_bus(service: Service) -> MockBus {
+    spawn_mock_bus(service, false, None, false)
+}
+
+/// Like `start_mock_bus`, but the unit can keep running until playpen stops
+/// it, and can write `output` to its stdout.
+fn start_mock_bus_with(service: Service, running: bool, output: Option<&'static str>) -> MockBus {
+    spawn_mock_bus(service, running, output, false)
+}
+
+/// Like `start_mock_bus`, but the unit's command really runs.
+fn start_executing_mock_bus(service: Service) -> MockBus {
+    spawn_mock_bus(service, false, None, true)
+}
+
+fn spawn_mock_bus(
+    service: Service,
+    running: bool,
+    output: Option<&'static str>,
+    execute: bool,
+) -> MockBus {
+    let dir = common::create_temp_dir();
+    let config = dir.path().join("bus.conf");
+    std::fs::write(
+        &config,
+        format!(
+            r#"<busconfig>
+  <type>session</type>
+  <listen>unix:dir={}</listen>
+  <auth>EXTERNAL</auth>
+  <policy context="default">
+    <allow send_destination="*" eavesdrop="true"/>
+    <allow eavesdrop="true"/>
+    <allow own="*"/>
+  </policy>
+</busconfig>"#,
+            dir.path().display()
+        ),
+    )
+    .unwrap();
+
+    let mut daemon = std::process::Command::new("dbus-daemon")
+        .arg(format!("--config-file={}", config.display()))
+        .args(["--nofork", "--print-address"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to start dbus-daemon");
+    let mut address = String::new();
+    BufReader::new(daemon.stdout.take().unwrap())
+        .read_line(&mut address)
+        .unwrap();
+    let address = address.trim().to_string();
+
+    let recorded = Recorded::default();
+    let started = Arc::new(Mutex::new(Vec::new()));
+    let active = Arc::new(AtomicBool::new(running));
+    let conn = Builder::address(address.as_str())
+        .unwrap()
+        .name("org.freedesktop.systemd1")
+        .unwrap()
+        .serve_at(
+            "/org/freedesktop/systemd1",
+            Manager {
+                recorded: recorded.clone(),
+                started: started.clone(),
+                active: active.clone(),
+                output,
+                execute,
+            },
+        )
+        .unwrap()
+        .serve_at(UNIT_PATH, Unit { active })
+        .unwrap()
+        .serve_at(UNIT_PATH, service)
+        .unwrap()
+        .build()
+        .expect("Failed to register mock systemd manager");
+
+    MockBus {
+        daemon,
+        address,
+        recorded,
+        started,
+        _conn: conn,
+        _dir: dir,
+    }
+}
+
+fn exited(status: i32) -> Service {
+    Service {
+        result: if status == 0 { "success" } else { "exit-code" },
+        code: 1,
+        status,
+        memory_peak: u64::MAX,
+    }
+}
+
+#[test]
+fn test_dbus_backend_returns_exit_status() {
+    if !common::dbus_daemon_available() {
+        eprintln!("Skipping D-Bus tests: dbus-daemon not available");
+        return;
+    }
+    let bus = start_mock_bus(exited(3));
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("DBUS_SESSION_BUS_ADDRESS", &bus.address);
+    cmd.args(["--backend", "dbus", "--", "true"]);
+
+    cmd.assert().code(3).stderr(predicate::str::contains(
+        "playpen: result: exit-code, main process: exited with status 3",
+    ));
+    let started = bus.started.lock().unwrap();
+    assert_eq!(started.len(), 1);
+    assert!(started[0].starts_with("playpen-") && started[0].ends_with(".service"));
+}
+
+#[test]
+fn test_dbus_backend_success() {
+    if !common::dbus_daemon_available() {
+        eprintln!("Skipping D-Bus tests: dbus-daemon not available");
+        return;
+    }
+    let bus = start_mock_bus(exited(0));
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("DBUS_SESSION_BUS_ADDRESS", &bus.address);
+    cmd.args(["--backend", "dbus", "--", "true"]);
+
+    cmd.assert().success();
+}
+
+#[test]
+fn test_dbus_backend_killed_by_signal() {
+    if !common::dbus_daemon_available() {
+        eprintln!("Skipping D-Bus tests: dbus-daemon not available");
+        return;
+    }
+    let bus = start_mock_bus(Service {
+        result: "signal",
+        code: 2,
+        status: 9,
+        memory_peak: u64::MAX,
+    });
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("DBUS_SESSION_BUS_ADDRESS", &bus.address);
+    cmd.args(["--backend", "dbus", "--", "true"]);
+
+    cmd.assert().code(137).stderr(predicate::str::contains(
+        "playpen: result: signal, main process: killed by SIGKILL\n",
+    ));
+}
+
+#[test]
+fn test_dbus_backend_oom_kill() {
+    if !common::dbus_daemon_available() {
+        eprintln!("Skipping D-Bus tests: dbus-daemon not available");
+        return;
+    }
+    // The kernel OOM killer sends SIGKILL, but the distinct exit code tells
+    // it apart from a plain `kill -9`.
+    let bus = start_mock_bus(Service {
+        result: "oom-kill",
+        code: 2,
+        status: 9,
+        memory_peak: 1 << 30,
+    });
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("DBUS_SESSION_BUS_ADDRESS", &bus.address);
+    cmd.args(["--backend", "dbus", "--", "true"]);
+
+    cmd.assert().code(123).stderr(predicate::str::contains(
+        "playpen: result: oom-kill, main process: killed by SIGKILL, memory peak: 1.0G",
+    ));
+}
+
+#[test]
+fn test_memory_limit_keeps_systemd_run_backend() {
+    // A memory limit alone, piped or not, still hands over to systemd-run;
+    // the D-Bus backend is only used when asked for or needed.
+    let bin = common::create_temp_dir();
+    let systemd_run = bin.path().join("systemd-run");
+    std::fs::write(&systemd_run, "#!/bin/sh\necho stand-in systemd-run\n").unwrap();
+    std::fs::set_permissions(&systemd_run, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("PATH", bin.path());
+    cmd.args(["-m", "1G", "--", "true"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("stand-in systemd-run"));
+}
+
+#[test]
+fn test_dbus_backend_timeout() {
+    if !common::dbus_daemon_available() {
+        eprintln!("Skipping D-Bus tests: dbus-daemon not available");
+        return;
+    }
+    let bus = start_mock_bus(Service {
+        result: "timeout",
+        code: 2,
+        status: 15,
+        memory_peak: u64::MAX,
+    });
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("DBUS_SESSION_BUS_ADDRESS", &bus.address);
+    cmd.args(["--backend", "dbus", "--", "true"]);
+
+    cmd.assert()
+        .code(124)
+        .stderr(predicate::str::contains("result: timeout"));
+}
+
+#[test]
+fn test_dbus_backend_sandbox_failure() {
+    if !common::dbus_daemon_available() {
+        eprintln!("Skipping D-Bus tests: dbus-daemon not available");
+        return;
+    }
+    // The main process never ran: systemd could not set up the unit.
+    let bus = start_mock_bus(Service {
+        result: "resources",
+        code: 0,
+        status: 0,
+        memory_peak: u64::MAX,
+    });
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("DBUS_SESSION_BUS_ADDRESS", &bus.address);
+    cmd.args(["--backend", "dbus", "--", "true"]);
+
+    cmd.assert().code(125).stderr(predicate::str::contains(
+        "playpen: result: resources, main process: not run",
+    ));
+}
+
+#[test]
+fn test_dbus_backend_quiet_suppresses_report() {
+    if !common::dbus_daemon_available() {
+        eprintln!("Skipping D-Bus tests: dbus-daemon not available");
+        return;
+    }
+    let bus = start_mock_bus(exited(3));
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("DBUS_SESSION_BUS_ADDRESS", &bus.address);
+    cmd.args(["--backend", "dbus", "--quiet", "--", "true"]);
+
+    cmd.assert()
+        .code(3)
+        .stderr(predicate::str::contains("playpen:").not());
+}
+
+#[test]
+fn test_dbus_backend_sends_typed_properties() {
+    if !common::dbus_daemon_available() {
+        eprintln!("Skipping D-Bus tests: dbus-daemon not available");
+        return;
+    }
+    let bus = start_mock_bus(exited(0));
+    let ro_dir = common::create_temp_dir();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("DBUS_SESSION_BUS_ADDRESS", &bus.address);
+    cmd.args([
+        "--backend",
+        "dbus",
+        "--protect-system",
+        "strict",
+        "--ro",
+        ro_dir.path().to_str().unwrap(),
+        "--memory-limit",
+        "1G",
+        "--memory-high",
+        "90%",
+        "--cpu-limit",
+        "150%",
+        "--tasks-max",
+        "300",
+        "--allowed-cpus",
+        "1,8-9",
+        "--cpu-weight",
+        "idle",
+        "--nice",
+        "5",
+        "--io-scheduling-class",
+        "idle",
+        "--disk-write-iops",
+        "200",
+        "--io-weight",
+        "50",
+        "--ip-deny",
+        "any",
+        "--socket-bind-deny",
+        "tcp:80",
+        "--",
+        "true",
+    ]);
+    cmd.assert().success();
+
+    let recorded = bus.recorded.lock().unwrap();
+    assert_eq!(recorded["MemoryMax"], "U64(1073741824)");
+    assert_eq!(recorded["MemoryHigh"], "U64(966367232)");
+    assert_eq!(recorded["CPUQuotaPerSecUSec"], "U64(1500000)");
+    assert_eq!(recorded["CPUQuotaPeriodUSec"], "U64(100000)");
+    assert_eq!(recorded["TasksMax"], "U64(300)");
+    // CPUs 1, 8 and 9 as a bitmask: 0b10 in byte 0, 0b11 in byte 1.
+    assert!(recorded["AllowedCPUs"].contains("U8(2), U8(3)"));
+    assert_eq!(recorded["CPUWeight"], "U64(0)");
+    assert_eq!(recorded["Nice"], "I32(5)");
+    assert_eq!(recorded["IOSchedulingClass"], "I32(3)");
+    assert!(recorded["IOWriteIOPSMax"].contains("U64(200)"));
+    assert_eq!(recorded["IOWeight"], "U64(50)");
+    assert_eq!(recorded["PrivateTmp"], "Bool(true)");
+    assert_eq!(recorded["ProtectSystem"], "Str(\"strict\")");
+    assert!(recorded["ExecStart"].contains("/true"));
+    // `any` expands to both the IPv4 and the IPv6 catch-all prefix.
+    assert!(recorded["IPAddressDeny"].contains("I32(2)"));
+    assert!(recorded["IPAddressDeny"].contains("I32(10)"));
+    assert!(recorded["SocketBindDeny"].contains("[I32(0), I32(6), U16(1), U16(80)]"));
+    assert!(recorded["BindReadOnlyPaths"].contains(ro_dir.path().to_str().unwrap()));
+    assert!(recorded["BindReadOnlyPaths"].contains("U64(16384)"));
+    assert!(recorded.contains_key("StandardOutputFileDescriptor"));
+    assert!(recorded.contains_key("WorkingDirectory"));
+}
+
+#[test]
+fn test_dbus_backend_command_not_found() {
+    if !common::dbus_daemon_available() {
+        eprintln!("Skipping D-Bus tests: dbus-daemon not available");
+        return;
+    }
+    let bus = start_mock_bus(exited(0));
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("DBUS_SESSION_BUS_ADDRESS", &bus.address);
+    cmd.args(["--backend", "dbus", "--", "no-such-command-playpen"]);
+
+    cmd.assert()
+        .code(125)
+        .stderr(predicate::str::contains("command not found"));
+    assert!(bus.started.lock().unwrap().is_empty());
+}
+
+// ============ --stats ============
+
+fn stats_service() -> Service {
+    Service {
+        result: "success",
+        code: 1,
+        status: 0,
+        memory_peak: 3 << 20,
+    }
+}
+
+#[test]
+fn test_dbus_backend_stats_text() {
+    if !common::dbus_daemon_available() {
+        eprintln!("Skipping D-Bus tests: dbus-daemon not available");
+        return;
+    }
+    let bus = start_mock_bus(stats_service());
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("DBUS_SESSION_BUS_ADDRESS", &bus.address);
+    cmd.args(["--stats", "--", "true"]);
+
+    cmd.assert().success().stderr(predicate::str::contains(
+        "playpen: memory peak: 3.0M\n\
+         playpen: CPU time: 1.500s\n\
+         playpen: IO read: 10.0M\n\
+         playpen: IO write: n/a\n\
+         playpen: tasks peak: 7\n",
+    ));
+    let recorded = bus.recorded.lock().unwrap();
+    assert_eq!(recorded["TasksAccounting"], "Bool(true)");
+}
+
+#[test]
+fn test_dbus_backend_stats_json_to_file() {
+    if !common::dbus_daemon_available() {
+        eprintln!("Skipping D-Bus tests: dbus-daemon not available");
+        return;
+    }
+    let bus = start_mock_bus(stats_service());
+    let out_dir = common::create_temp_dir();
+    let out = out_dir.path().join("stats.json");
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("DBUS_SESSION_BUS_ADDRESS", &bus.address);
+    cmd.args(["--stats=json", "--stats-file", out.to_str().unwrap()]);
+    cmd.args(["--", "true"]);
+
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("memory_peak_bytes").not());
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&out).unwrap()).unwrap();
+    assert_eq!(json["result"], "success");
+    assert_eq!(json["exit_code"], 0);
+    assert_eq!(json["memory_peak_bytes"], 3 << 20);
+    assert_eq!(json["cpu_usage_nsec"], 1_500_000_000u64);
+    assert_eq!(json["io_read_bytes"], 10 << 20);
+    assert!(json["io_write_bytes"].is_null());
+    assert_eq!(json["tasks_peak"], 7);
+}
+
+// ============ suggest ============
+
+#[test]
+fn test_suggest_prints_loadable_profile() {
+    if !common::dbus_daemon_available() {
+        eprintln!("Skipping D-Bus tests: dbus-daemon not available");
+        return;
+    }
+    let bus = start_mock_bus(stats_service());
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("DBUS_SESSION_BUS_ADDRESS", &bus.address);
+    cmd.args(["suggest", "--name", "build", "--", "true"]);
+
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let stdout = String::from_utf8(output).unwrap();
+    // 3M peak with 50% headroom, rounded up to a whole megabyte.
+    assert!(stdout.contains("memory_limit = \"5M\"\n"), "{}", stdout);
+    assert!(stdout.contains("memory_swap_max = \"0\"\n"), "{}", stdout);
+    assert!(stdout.contains("cpu_quota = "), "{}", stdout);
+    // 7 tasks with 50% headroom, rounded up to a multiple of 64.
+    assert!(stdout.contains("tasks_max = \"64\"\n"), "{}", stdout);
+    assert!(stdout.contains("disk_read = "), "{}", stdout);
+    // The mock reports no write counter, so no write limit is suggested.
+    assert!(!stdout.contains("disk_write"), "{}", stdout);
+    // Nothing but the measured unit's accounting was requested.
+    let recorded = bus.recorded.lock().unwrap();
+    assert!(!recorded.contains_key("MemoryMax"));
+    assert_eq!(recorded["MemoryAccounting"], "Bool(true)");
+
+    // The output pastes straight into a profiles file.
+    let config = common::create_config_dir(&stdout);
+    let mut show = Command::new(common::get_playpen_path());
+    show.env("XDG_CONFIG_HOME", config.path());
+    show.args(["profiles", "show", "build"]);
+    show.assert()
+        .success()
+        .stdout(predicate::str::is_match(r"memory_limit\s+5M").unwrap());
+}
+
+#[test]
+fn test_suggest_names_profile_after_command() {
+    if !common::dbus_daemon_available() {
+        eprintln!("Skipping D-Bus tests: dbus-daemon not available");
+        return;
+    }
+    let bus = start_mock_bus(stats_service());
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("DBUS_SESSION_BUS_ADDRESS", &bus.address);
+    cmd.args(["suggest", "--headroom", "2", "--", "/usr/bin/true"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("name = \"true\"\n"))
+        .stdout(predicate::str::contains("memory_limit = \"6M\"\n"))
+        .stdout(predicate::str::contains("100% headroom"));
+}
+
+#[test]
+fn test_suggest_reports_failed_command() {
+    if !common::dbus_daemon_available() {
+        eprintln!("Skipping D-Bus tests: dbus-daemon not available");
+        return;
+    }
+    let bus = start_mock_bus(exited(2));
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("DBUS_SESSION_BUS_ADDRESS", &bus.address);
+    cmd.args(["suggest", "--", "true"]);
+
+    cmd.assert()
+        .code(2)
+        .stdout(predicate::str::contains("[[profile]]"))
+        .stderr(predicate::str::contains("did not succeed"));
+}
+
+#[test]
+fn test_suggest_rejects_headroom_below_one() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["suggest", "--headroom", "0.5", "--", "true"]);
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("--headroom must be at least 1.0"));
+}
+
+#[test]
+fn test_dbus_backend_idle_timeout() {
+    if !common::dbus_daemon_available() {
+        eprintln!("Skipping D-Bus tests: dbus-daemon not available");
+        return;
+    }
+    // Stopped by playpen, as systemd reports a unit it was asked to stop.
+    let service = Service {
+        result: "success",
+        code: 2,
+        status: 15,
+        memory_peak: u64::MAX,
+    };
+    let bus = start_mock_bus_with(service, true, Some("hello from the unit\n"));
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("DBUS_SESSION_BUS_ADDRESS", &bus.address);
+    cmd.args(["--backend", "dbus", "--idle-timeout", "300ms", "--", "true"]);
+
+    cmd.assert()
+        .code(124)
+        .stdout(predicate::str::contains("hello from the unit"))
+        .stderr(predicate::str::contains("playpen: result: idle-timeout"));
+}
+
+#[test]
+fn test_dbus_backend_idle_timeout_not_reached() {
+    if !common::dbus_daemon_available() {
+        eprintln!("Skipping D-Bus tests: dbus-daemon not available");
+        return;
+    }
+    let bus = start_mock_bus_with(exited(0), false, Some("done\n"));
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("DBUS_SESSION_BUS_ADDRESS", &bus.address);
+    cmd.args(["--idle-timeout", "1m", "--", "true"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("done"))
+        .stderr(predicate::str::contains("result: success"));
+}
+
+#[test]
+fn test_dbus_backend_idle_timeout_exits_promptly() {
+    if !common::dbus_daemon_available() {
+        eprintln!("Skipping D-Bus tests: dbus-daemon not available");
+        return;
+    }
+    // The relays see end-of-file as soon as the unit's output is closed,
+    // rather than waiting out the two-second drain deadline.
+    let bus = start_mock_bus_with(exited(0), false, Some("done\n"));
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("DBUS_SESSION_BUS_ADDRESS", &bus.address);
+    cmd.args(["--idle-timeout", "1m", "--", "true"]);
+
+    let started = std::time::Instant::now();
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("done"));
+    assert!(
+        started.elapsed() < std::time::Duration::from_secs(1),
+        "took {:?}",
+        started.elapsed()
+    );
+}
+
+#[test]
+fn test_dbus_backend_timeout_property() {
+    if !common::dbus_daemon_available() {
+        eprintln!("Skipping D-Bus tests: dbus-daemon not available");
+        return;
+    }
+    let bus = start_mock_bus(exited(0));
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("DBUS_SESSION_BUS_ADDRESS", &bus.address);
+    cmd.args(["--timeout", "1h 30m", "--", "true"]);
+
+    cmd.assert().success();
+    let recorded = bus.recorded.lock().unwrap();
+    assert_eq!(recorded["RuntimeMaxUSec"], "U64(5400000000)");
+}
+
+// ============ Environment round trips ============
+
+/// Run `printenv NAME` in a unit through the executing mock, with `value`
+/// in playpen's environment, and return what the command saw.
+fn printenv_round_trip(value: &std::ffi::OsStr) -> Vec<u8> {
+    let bus = start_executing_mock_bus(exited(0));
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("DBUS_SESSION_BUS_ADDRESS", &bus.address);
+    cmd.env("PLAYPEN_ROUND_TRIP", value);
+    cmd.args([
+        "--backend",
+        "dbus",
+        "--quiet",
+        "--env-allow",
+        "PLAYPEN_ROUND_TRIP",
+        "--",
+        "printenv",
+        "PLAYPEN_ROUND_TRIP",
+    ]);
+    let output = cmd.output().unwrap();
+    assert!(
+        output.status.success(),
+        "playpen failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output.stdout
+}
+
+#[test]
+fn test_env_values_round_trip_unchanged() {
+    if !common::dbus_daemon_available() {
+        eprintln!("Skipping D-Bus tests: dbus-daemon not available");
+        return;
+    }
+    for value in [
+        "plain",
+        r#"say "hi" and 'bye'"#,
+        "two\nlines\n",
+        "$HOME `id` ; * \\",
+        "",
+    ] {
+        let seen = printenv_round_trip(value.as_ref());
+        assert_eq!(
+            seen,
+            format!("{}\n", value).into_bytes(),
+            "value {:?}",
+            value
+        );
+    }
+}
+
+#[test]
+fn test_non_utf8_env_value_round_trips() {
+    if !common::dbus_daemon_available() {
+        eprintln!("Skipping D-Bus tests: dbus-daemon not available");
+        return;
+    }
+    use std::os::unix::ffi::OsStrExt;
+    let bytes = b"caf\xe9 \xff'%s\\n\n";
+    let seen = printenv_round_trip(std::ffi::OsStr::from_bytes(bytes));
+    assert_eq!(seen, [&bytes[..], b"\n"].concat());
+}
+
+#[test]
+fn test_setenv_round_trips_quotes() {
+    if !common::dbus_daemon_available() {
+        eprintln!("Skipping D-Bus tests: dbus-daemon not available");
+        return;
+    }
+    let bus = start_executing_mock_bus(exited(0));
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("DBUS_SESSION_BUS_ADDRESS", &bus.address);
+    cmd.args([
+        "--backend",
+        "dbus",
+        "--quiet",
+        "--setenv",
+        r#"GREETING="quoted""#,
+        "--",
+        "printenv",
+        "GREETING",
+    ]);
+
+    cmd.assert().success().stdout("\"quoted\"\n");
+}
@@ -5,7 +5,7 @@ mod common;
 
 // All tests use --dry-run, so they inspect the rendered systemd-run command
 // without needing a real cgroup. The disk-I/O properties carry the block
-// device backing the working directory, which dry-run renders as the test's
+// device backing the project directory, which dry-run renders as the test's
 // current directory; the assertions match only the property name and rate.
 
 // ============ --disk-limit (covers both directions) ============
@@ -114,3 +114,261 @@ fn test_disk_limit_with_profile() {
         .stdout(predicate::str::is_match(r"IOReadBandwidthMax=\S+ 20M").unwrap())
         .stdout(predicate::str::is_match(r"IOWriteBandwidthMax=\S+ 20M").unwrap());
 }
+
+// ============ Per-path limits ============
+
+#[test]
+fn test_disk_limit_for_one_path() {
+    let data = common::create_temp_dir();
+    let path = data.path().to_str().unwrap();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--disk-write",
+        &format!("{}=20M", path),
+        "--dry-run",
+        "--",
+        "echo",
+        "hi",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "-pIOWriteBandwidthMax={} 20M",
+            path
+        )))
+        .stdout(predicate::str::contains("IOReadBandwidthMax").not());
+}
+
+#[test]
+fn test_disk_limit_relative_path_made_absolute() {
+    let project = common::create_temp_dir();
+    std::fs::create_dir(project.path().join("data")).unwrap();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.current_dir(project.path());
+    cmd.args(["--disk-read", "data=5M", "--dry-run", "--", "echo", "hi"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "-pIOReadBandwidthMax={} 5M",
+            project.path().join("data").display()
+        )));
+}
+
+#[test]
+fn test_disk_limit_missing_path_rejected() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--disk-write",
+        "/nonexistent/playpen-data=20M",
+        "--dry-run",
+        "--",
+        "echo",
+        "hi",
+    ]);
+
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "no such path: /nonexistent/playpen-data",
+    ));
+}
+
+#[test]
+fn test_per_path_limit_wins_on_its_device() {
+    // The working directory is on the same device as the per-path limit, so
+    // the plain ceiling is not emitted for it as well.
+    let project = common::create_temp_dir();
+    let path = project.path().to_str().unwrap();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.current_dir(project.path());
+    cmd.args([
+        "--disk-write",
+        "50M",
+        "--disk-write",
+        &format!("{}=20M", path),
+        "--dry-run",
+        "--",
+        "echo",
+        "hi",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "-pIOWriteBandwidthMax={} 20M",
+            path
+        )))
+        .stdout(predicate::str::contains("50M").not());
+}
+
+// ============ Read-write binds ============
+
+#[test]
+fn test_disk_limit_applies_to_rw_bind_device() {
+    // /proc is never on the same device as the working directory, so it
+    // stands in for a --rw path on another disk.
+    let project = common::create_temp_dir();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.current_dir(project.path());
+    cmd.args([
+        "-d",
+        "50M",
+        "--rw",
+        "/proc",
+        "--dry-run",
+        "--",
+        "echo",
+        "hi",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "-pIOWriteBandwidthMax={} 50M",
+            project.path().display()
+        )))
+        .stdout(predicate::str::contains("-pIOWriteBandwidthMax=/proc 50M"))
+        .stdout(predicate::str::contains("-pIOReadBandwidthMax=/proc 50M"));
+}
+
+#[test]
+fn test_disk_limit_follows_project_root() {
+    // The limit names the device behind the project directory, not the
+    // directory playpen was started from.
+    let pwd = common::create_temp_dir();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.current_dir(pwd.path());
+    cmd.args([
+        "-d",
+        "50M",
+        "--project-root",
+        "/proc",
+        "--dry-run",
+        "--",
+        "echo",
+        "hi",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pIOWriteBandwidthMax=/proc 50M"))
+        .stdout(predicate::str::contains(pwd.path().display().to_string()).not());
+}
+
+#[test]
+fn test_disk_limit_once_per_device() {
+    // A bind inside the working directory shares its device, so the limit
+    // is emitted once.
+    let project = common::create_temp_dir();
+    std::fs::create_dir(project.path().join("target")).unwrap();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.current_dir(project.path());
+    cmd.args([
+        "--disk-write",
+        "5M",
+        "--rw",
+        project.path().join("target").to_str().unwrap(),
+        "--dry-run",
+        "--",
+        "echo",
+        "hi",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("IOWriteBandwidthMax").count(1));
+}
+
+// ============ IOPS and weight ============
+
+#[test]
+fn test_disk_iops_limits() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--disk-read-iops",
+        "1000",
+        "--disk-write-iops",
+        "infinity",
+        "--dry-run",
+        "--",
+        "echo",
+        "hi",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(r"IOReadIOPSMax=\S+ 1000").unwrap())
+        .stdout(predicate::str::is_match(r"IOWriteIOPSMax=\S+ infinity").unwrap());
+}
+
+#[test]
+fn test_invalid_iops_rejected() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["--disk-read-iops", "fast", "--dry-run", "--", "echo", "hi"]);
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid IOPS limit: fast"));
+}
+
+#[test]
+fn test_io_weight() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["--io-weight", "50", "--dry-run", "--", "echo", "hi"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pIOWeight=50"));
+}
+
+#[test]
+fn test_io_weight_out_of_range_rejected() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["--io-weight", "0", "--dry-run", "--", "echo", "hi"]);
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid IO weight: 0"));
+}
+
+#[test]
+fn test_profile_iops_and_weight() {
+    let config = common::create_config_dir(
+        "[[profile]]\nname = \"quiet\"\ndisk_write_iops = \"200\"\nio_weight = \"10\"\n",
+    );
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args(["--profile", "quiet", "--dry-run", "--", "echo", "hi"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(r"IOWriteIOPSMax=\S+ 200").unwrap())
+        .stdout(predicate::str::contains("-pIOWeight=10"));
+}
+
+#[test]
+fn test_per_path_limit_explained() {
+    let data = common::create_temp_dir();
+    let path = data.path().to_str().unwrap();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--disk-write",
+        &format!("{}=20M", path),
+        "--explain",
+        "--",
+        "echo",
+        "hi",
+    ]);
+
+    cmd.assert().success().stdout(
+        predicate::str::is_match(format!(r"IOWriteBandwidthMax={} 20M\s+cli", path)).unwrap(),
+    );
+}
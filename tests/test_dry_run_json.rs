@@ -0,0 +1,152 @@
+use assert_cmd::Command;
+use serde_json::Value;
+
+mod common;
+
+// `--dry-run=json` prints the resolved configuration; these tests parse it
+// instead of pattern-matching the shell-quoted text form.
+
+fn dry_run_json(cmd: &mut Command) -> Value {
+    let output = cmd.output().unwrap();
+    assert!(
+        output.status.success(),
+        "playpen failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    serde_json::from_slice(&output.stdout).expect("dry-run output is JSON")
+}
+
+/// The `source` of the value that won for a scalar field.
+fn winning_source(report: &Value, field: &str) -> String {
+    report["sources"][field]
+        .as_array()
+        .and_then(|entries| entries.last())
+        .map(|entry| entry["source"].as_str().unwrap().to_string())
+        .unwrap_or_else(|| panic!("no source recorded for {}", field))
+}
+
+#[test]
+fn test_json_contains_config_and_argv() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "-m",
+        "1G",
+        "-c",
+        "50%",
+        "--dry-run=json",
+        "--",
+        "echo",
+        "hi",
+    ]);
+
+    let report = dry_run_json(&mut cmd);
+    assert_eq!(report["config"]["memory_max"], "1G");
+    assert_eq!(report["config"]["cpu_quota"], "50%");
+    assert_eq!(report["config"]["protect_home"], Value::Null);
+    assert_eq!(report["config"]["private_tmp"], true);
+
+    let argv: Vec<&str> = report["argv"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| v.as_str().unwrap())
+        .collect();
+    assert_eq!(argv[0], "systemd-run");
+    assert!(argv.contains(&"-pMemoryMax=1G"));
+    assert_eq!(&argv[argv.len() - 2..], ["echo", "hi"]);
+}
+
+#[test]
+fn test_json_sources_track_overrides() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--profile",
+        "cargo",
+        "-m",
+        "4G",
+        "--dry-run=json",
+        "--",
+        "cargo",
+        "build",
+    ]);
+
+    let report = dry_run_json(&mut cmd);
+    // memory_max: the profile's 2G, then the CLI's 4G, in that order.
+    let memory = report["sources"]["memory_max"].as_array().unwrap();
+    assert_eq!(memory.len(), 2);
+    assert_eq!(memory[0]["source"], "profile:cargo");
+    assert_eq!(memory[0]["value"], "2G");
+    assert_eq!(memory[1]["source"], "cli");
+    assert_eq!(memory[1]["value"], "4G");
+
+    assert_eq!(winning_source(&report, "cpu_quota"), "profile:cargo");
+    assert_eq!(winning_source(&report, "private_tmp"), "default");
+}
+
+#[test]
+fn test_json_sources_preset_and_project_file() {
+    let project = common::create_temp_dir();
+    std::fs::write(
+        project.path().join(".playpen.toml"),
+        "protect_home = \"read-only\"\ncpu_quota = \"75%\"\n",
+    )
+    .unwrap();
+    let config = common::trust_project_file(project.path());
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.current_dir(project.path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args(["--current-dir-only", "--dry-run=json", "--", "true"]);
+
+    let report = dry_run_json(&mut cmd);
+    assert_eq!(report["config"]["protect_home"], "tmpfs");
+    assert_eq!(
+        winning_source(&report, "protect_home"),
+        "preset:current-dir-only"
+    );
+    assert!(winning_source(&report, "cpu_quota").starts_with("project:"));
+    assert!(winning_source(&report, "cpu_quota").ends_with(".playpen.toml"));
+}
+
+#[test]
+fn test_json_default_swap_source() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["-m", "50M", "--dry-run=json", "--", "true"]);
+
+    let report = dry_run_json(&mut cmd);
+    assert_eq!(report["config"]["memory_swap_max"], "0");
+    assert_eq!(winning_source(&report, "memory_swap_max"), "default");
+}
+
+#[test]
+fn test_json_list_sources() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--rw",
+        "-/tmp/a",
+        "--ip-deny",
+        "any",
+        "--dry-run=json",
+        "--",
+        "true",
+    ]);
+
+    let report = dry_run_json(&mut cmd);
+    assert_eq!(report["config"]["bind_paths"][0], "-/tmp/a");
+    assert_eq!(report["sources"]["bind_paths"][0]["source"], "cli");
+    assert_eq!(report["sources"]["ip_deny"][0]["value"], "any");
+}
+
+#[test]
+fn test_bare_dry_run_is_text() {
+    // Without a value, --dry-run keeps printing the shell-quoted command, and
+    // does not swallow the command that follows it.
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["--dry-run", "echo", "hi"]);
+
+    let output = cmd.output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("systemd-run "));
+    assert!(stdout.trim_end().ends_with("echo hi"));
+}
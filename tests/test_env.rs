@@ -0,0 +1,293 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+mod common;
+
+// Environment forwarding, checked through the `--setenv=` arguments that
+// `--dry-run` prints.
+
+#[test]
+fn test_only_path_forwarded_by_default() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("PLAYPEN_TEST_VAR", "hello");
+    cmd.args(["--dry-run", "--", "echo", "hi"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("--setenv=PATH="))
+        .stdout(predicate::str::contains("PLAYPEN_TEST_VAR").not());
+}
+
+#[test]
+fn test_env_allow_pattern_forwards_matching_variables() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("PLAYPEN_TEST_ONE", "1");
+    cmd.env("PLAYPEN_TEST_TWO", "2");
+    cmd.env("PLAYPEN_OTHER", "3");
+    cmd.args([
+        "--env-allow",
+        "PLAYPEN_TEST_*",
+        "--dry-run",
+        "--",
+        "echo",
+        "hi",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("PLAYPEN_TEST_ONE"))
+        .stdout(predicate::str::contains("PLAYPEN_TEST_TWO"))
+        .stdout(predicate::str::contains("PLAYPEN_OTHER").not());
+}
+
+#[test]
+fn test_env_deny_beats_capture_env() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("PLAYPEN_KEEP", "1");
+    cmd.env("PLAYPEN_DROP_ME", "2");
+    cmd.args([
+        "--capture-env=on",
+        "--env-deny",
+        "PLAYPEN_DROP_*",
+        "--dry-run",
+        "--",
+        "echo",
+        "hi",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("PLAYPEN_KEEP"))
+        .stdout(predicate::str::contains("PLAYPEN_DROP_ME").not());
+}
+
+#[test]
+fn test_secrets_denied_even_with_capture_env() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("AWS_SECRET_ACCESS_KEY", "hunter2");
+    cmd.env("GITHUB_TOKEN", "ghp_hunter2");
+    cmd.env("PLAYPEN_HARMLESS", "1");
+    cmd.args(["--capture-env=on", "--dry-run", "--", "echo", "hi"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("PLAYPEN_HARMLESS"))
+        .stdout(predicate::str::contains("hunter2").not());
+}
+
+#[test]
+fn test_secret_allowed_by_exact_name_only() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("CARGO_REGISTRY_TOKEN", "cio_secret");
+    cmd.env("CARGO_HOME", "/opt/cargo");
+    cmd.env("NPM_TOKEN", "npm_secret");
+    cmd.args([
+        "--env-allow",
+        "CARGO_*",
+        "--env-allow",
+        "NPM_TOKEN",
+        "--dry-run",
+        "--",
+        "echo",
+        "hi",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("CARGO_HOME"))
+        .stdout(predicate::str::contains("cio_secret").not())
+        .stdout(predicate::str::contains("NPM_TOKEN"));
+}
+
+#[test]
+fn test_setenv() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--setenv",
+        "RUST_LOG=debug",
+        "--dry-run",
+        "--",
+        "echo",
+        "hi",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("--setenv=RUST_LOG="))
+        .stdout(predicate::str::contains("debug"));
+}
+
+#[test]
+fn test_invalid_setenv_rejected() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["--setenv", "NOVALUE", "--dry-run", "--", "echo", "hi"]);
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid assignment: NOVALUE"));
+}
+
+#[test]
+fn test_invalid_env_pattern_rejected() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["--env-allow", "FOO-BAR", "--dry-run", "--", "echo", "hi"]);
+
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "invalid variable pattern: FOO-BAR",
+    ));
+}
+
+#[test]
+fn test_env_file() {
+    let dir = common::create_temp_dir();
+    let file = dir.path().join(".env.sandbox");
+    std::fs::write(
+        &file,
+        "# comment\n\nDATABASE_URL=postgres://localhost/dev\nexport MODE='test'\n",
+    )
+    .unwrap();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--env-file",
+        file.to_str().unwrap(),
+        "--dry-run",
+        "--",
+        "echo",
+        "hi",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("DATABASE_URL="))
+        .stdout(predicate::str::contains("postgres://localhost/dev"))
+        .stdout(predicate::str::contains("MODE="))
+        .stdout(predicate::str::contains("comment").not());
+}
+
+#[test]
+fn test_env_file_malformed_line_reported() {
+    let dir = common::create_temp_dir();
+    let file = dir.path().join(".env.sandbox");
+    std::fs::write(&file, "GOOD=1\nnot an assignment\n").unwrap();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--env-file",
+        file.to_str().unwrap(),
+        "--dry-run",
+        "--",
+        "echo",
+        "hi",
+    ]);
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains(":2: expected NAME=VALUE"));
+}
+
+#[test]
+fn test_profile_env_allow() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("RUSTFLAGS", "-Dwarnings");
+    cmd.env("PLAYPEN_UNRELATED", "1");
+    cmd.args(["--profile", "cargo", "--dry-run", "--", "cargo", "build"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("--setenv=RUSTFLAGS="))
+        .stdout(predicate::str::contains("PLAYPEN_UNRELATED").not());
+}
+
+#[test]
+fn test_user_profile_env_deny() {
+    let config =
+        common::create_config_dir("[[profile]]\nname = \"locked\"\nenv_deny = [\"PLAYPEN_*\"]\n");
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.env("PLAYPEN_PRIVATE", "1");
+    cmd.args([
+        "--profile",
+        "locked",
+        "--capture-env=on",
+        "--dry-run",
+        "--",
+        "echo",
+        "hi",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("PLAYPEN_PRIVATE").not());
+}
+
+// ============ Values passed exactly ============
+
+/// The argv that `cmd`, a `--dry-run=json` invocation, reports.
+fn dry_run_argv(cmd: &mut Command) -> Vec<String> {
+    let output = cmd.output().unwrap();
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    serde_json::from_value(report["argv"].clone()).unwrap()
+}
+
+#[test]
+fn test_forwarded_value_not_quoted() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("PLAYPEN_QUOTED", "say \"hi\"\nand 'bye'");
+    cmd.args([
+        "--env-allow",
+        "PLAYPEN_QUOTED",
+        "--dry-run=json",
+        "--",
+        "echo",
+        "hi",
+    ]);
+
+    let argv = dry_run_argv(&mut cmd);
+    assert!(argv.contains(&"--setenv=PLAYPEN_QUOTED=say \"hi\"\nand 'bye'".to_string()));
+}
+
+#[test]
+fn test_non_utf8_value_set_by_wrapper() {
+    use std::os::unix::ffi::OsStrExt;
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("PLAYPEN_BYTES", std::ffi::OsStr::from_bytes(b"\xff\n"));
+    cmd.args([
+        "--env-allow",
+        "PLAYPEN_BYTES",
+        "--dry-run=json",
+        "--",
+        "echo",
+        "hi",
+    ]);
+
+    let argv = dry_run_argv(&mut cmd);
+    assert!(!argv.iter().any(|a| a.starts_with("--setenv=PLAYPEN_BYTES")));
+    let sh = argv
+        .iter()
+        .position(|a| a == "/bin/sh")
+        .expect("sh wrapper");
+    assert_eq!(argv[sh + 1], "-c");
+    assert!(argv[sh + 2].contains(r"printf '\377\012x'"));
+    assert_eq!(argv[sh + 3..], ["sh", "echo", "hi"]);
+}
+
+#[test]
+#[ignore = "requires a systemd user manager; run locally with: cargo test -- --include-ignored"]
+fn test_env_round_trip_through_systemd_run() {
+    let value = "say \"hi\"\n$HOME 'quoted' \\n";
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("PLAYPEN_ROUND_TRIP", value);
+    cmd.args([
+        "--quiet",
+        "--env-allow",
+        "PLAYPEN_ROUND_TRIP",
+        "--",
+        "printenv",
+        "PLAYPEN_ROUND_TRIP",
+    ]);
+
+    cmd.assert().success().stdout(format!("{}\n", value));
+}
@@ -0,0 +1,185 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+mod common;
+
+// `--explain` prints one line per emitted property naming the layer that set
+// it, then any settings turned off and any skipped profile paths. Like
+// --dry-run it never executes systemd-run.
+
+/// The explanation line for the first property starting with `property`.
+fn line_for(stdout: &str, property: &str) -> String {
+    stdout
+        .lines()
+        .find(|l| l.trim_start().starts_with(property))
+        .unwrap_or_else(|| panic!("no line for {} in:\n{}", property, stdout))
+        .to_string()
+}
+
+fn explain(cmd: &mut Command) -> String {
+    let output = cmd.output().unwrap();
+    assert!(
+        output.status.success(),
+        "playpen failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_explain_cli_overrides_profile() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--profile",
+        "cargo",
+        "-m",
+        "4G",
+        "--explain",
+        "--",
+        "cargo",
+        "build",
+    ]);
+
+    let stdout = explain(&mut cmd);
+    let memory = line_for(&stdout, "MemoryMax=4G");
+    assert!(memory.contains("cli"));
+    assert!(memory.contains("overrides profile:cargo=2G"));
+    assert!(line_for(&stdout, "CPUQuota=300%").contains("profile:cargo"));
+    assert!(line_for(&stdout, "PrivateTmp=yes").contains("default"));
+    // The explanation replaces the run; no command line is printed.
+    assert!(!stdout.contains("systemd-run"));
+}
+
+#[test]
+fn test_explain_preset_overrides_profile_protect_home() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--profile",
+        "shell",
+        "--current-dir-only",
+        "--explain",
+        "--",
+        "bash",
+    ]);
+
+    let stdout = explain(&mut cmd);
+    let line = line_for(&stdout, "ProtectHome=tmpfs");
+    assert!(line.contains("preset:current-dir-only"));
+    assert!(line.contains("overrides profile:shell=read-only"));
+}
+
+#[test]
+fn test_explain_same_project_bind_not_an_override() {
+    // Both profiles bind the project with `project_root = "auto"`; the
+    // second changes nothing, so the first keeps the credit.
+    let project = common::create_temp_dir();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.current_dir(project.path());
+    cmd.args([
+        "--profile",
+        "cargo",
+        "--profile",
+        "npm",
+        "--explain",
+        "--",
+        "true",
+    ]);
+
+    let stdout = explain(&mut cmd);
+    let line = line_for(&stdout, &format!("BindPaths={}", project.path().display()));
+    assert!(line.ends_with("profile:cargo"), "{}", line);
+}
+
+#[test]
+fn test_explain_list_entries_name_their_layer() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--rw",
+        "-/tmp/extra",
+        "--ip-deny",
+        "any",
+        "--explain",
+        "--",
+        "true",
+    ]);
+
+    let stdout = explain(&mut cmd);
+    assert!(line_for(&stdout, "BindPaths=-/tmp/extra").ends_with("cli"));
+    assert!(line_for(&stdout, "IPAddressDeny=any").ends_with("cli"));
+}
+
+#[test]
+fn test_explain_lists_disabled_settings() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--profile",
+        "cargo",
+        "--protect-home",
+        "none",
+        "--private-tmp",
+        "false",
+        "--explain",
+        "--",
+        "true",
+    ]);
+
+    let stdout = explain(&mut cmd);
+    assert!(stdout.contains("Not emitted:"));
+    let home = line_for(&stdout, "protect_home = none");
+    assert!(home.contains("cli (overrides profile:cargo=tmpfs)"));
+    assert!(line_for(&stdout, "private_tmp = false").contains("overrides default=true"));
+    assert!(!stdout.contains("ProtectHome="));
+}
+
+#[test]
+fn test_explain_lists_skipped_profile_paths() {
+    // An empty home: none of cargo's paths exist.
+    let temp_home = common::create_temp_dir();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("HOME", temp_home.path());
+    cmd.args(["--profile", "cargo", "--explain", "--", "cargo", "build"]);
+
+    let home = temp_home.path().display().to_string();
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Skipped paths (do not exist):"))
+        .stdout(
+            predicate::str::is_match(format!(
+                r"(?m)^\s+{}/\.cargo\s+bind_paths from profile:cargo$",
+                common::regex_escape(&home)
+            ))
+            .unwrap(),
+        )
+        .stdout(
+            predicate::str::is_match(format!(
+                r"(?m)^\s+{}/\.rustup\s+bind_ro_paths from profile:cargo$",
+                common::regex_escape(&home)
+            ))
+            .unwrap(),
+        );
+}
+
+#[test]
+fn test_dry_run_json_includes_skipped_paths() {
+    let temp_home = common::create_temp_dir();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("HOME", temp_home.path());
+    cmd.args([
+        "--profile",
+        "cargo",
+        "--dry-run=json",
+        "--",
+        "cargo",
+        "build",
+    ]);
+
+    let output = cmd.output().unwrap();
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let skipped = report["skipped_paths"].as_array().unwrap();
+    assert!(skipped.iter().any(
+        |s| s["path"].as_str().unwrap().ends_with("/.cargo") && s["source"] == "profile:cargo"
+    ));
+}
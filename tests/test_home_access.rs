@@ -7,7 +7,7 @@ mod common;
 fn test_no_protection_home_access() {
     // Test that without protection, we can see home directory contents
     let output = Command::new(common::get_playpen_path())
-        .args(&["--protect-home=none", "--", "sh", "-c", "ls /home | wc -l"])
+        .args(["--protect-home=none", "--", "sh", "-c", "ls /home | wc -l"])
         .output()
         .expect("Failed to execute playpen");
 
@@ -31,7 +31,7 @@ fn test_protect_home_tmpfs() {
     // Run from root directory to avoid conflicts with protection
     let output = Command::new(common::get_playpen_path())
         .current_dir("/")
-        .args(&["--protect-home=tmpfs", "--", "ls", "/home"])
+        .args(["--protect-home=tmpfs", "--", "ls", "/home"])
         .output()
         .expect("Failed to execute playpen");
 
@@ -80,7 +80,7 @@ except Exception as e:
 
     let output = Command::new(common::get_playpen_path())
         .current_dir(temp_dir.path())
-        .args(&["--current-dir-only", "--", "python3", "test_home.py"])
+        .args(["--current-dir-only", "--", "python3", "test_home.py"])
         .output()
         .expect("Failed to execute playpen");
 
@@ -103,7 +103,7 @@ fn test_current_dir_only_allows_pwd() {
 
     let output = Command::new(common::get_playpen_path())
         .current_dir(temp_dir.path())
-        .args(&["--current-dir-only", "--", "cat", "test_file.txt"])
+        .args(["--current-dir-only", "--", "cat", "test_file.txt"])
         .output()
         .expect("Failed to execute playpen");
 
@@ -137,7 +137,7 @@ fn test_current_dir_only_blocks_sensitive_files() {
     for path in sensitive_paths {
         let output = Command::new(common::get_playpen_path())
             .current_dir(temp_dir.path())
-            .args(&[
+            .args([
                 "--current-dir-only",
                 "--",
                 "sh",
@@ -168,7 +168,7 @@ fn test_fine_grained_ro_access() {
     // Test read-only access to /etc from root directory to avoid conflicts
     let output = Command::new(common::get_playpen_path())
         .current_dir("/")
-        .args(&["--ro", "/etc", "--", "ls", "/etc/passwd"])
+        .args(["--ro", "/etc", "--", "ls", "/etc/passwd"])
         .output()
         .expect("Failed to execute playpen");
 
@@ -184,7 +184,7 @@ fn test_fine_grained_ro_access() {
     // Test that we can't write to the read-only path
     let output = Command::new(common::get_playpen_path())
         .current_dir("/")
-        .args(&[
+        .args([
             "--ro",
             "/etc",
             "--",
@@ -211,7 +211,7 @@ fn test_fine_grained_rw_access() {
 
     // Test read-write access to temp directory
     let output = Command::new(common::get_playpen_path())
-        .args(&[
+        .args([
             "--rw",
             temp_dir.path().to_str().unwrap(),
             "--",
@@ -246,7 +246,7 @@ fn test_inaccessible_paths() {
     // Test that inaccessible paths are blocked
     let output = Command::new(common::get_playpen_path())
         .current_dir(temp_dir.path())
-        .args(&["--inaccessible", "/etc", "--", "ls", "/etc"])
+        .args(["--inaccessible", "/etc", "--", "ls", "/etc"])
         .output()
         .expect("Failed to execute playpen");
 
@@ -265,7 +265,7 @@ fn test_memory_limit_still_works() {
     // Test that memory limits still work with new path features
     let output = Command::new(common::get_playpen_path())
         .current_dir(temp_dir.path())
-        .args(&[
+        .args([
             "-m",
             "50M",
             "--current-dir-only",
@@ -0,0 +1,247 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+use tempfile::TempDir;
+
+mod common;
+
+/// A project directory with a few secrets among ordinary files.
+fn project_with_secrets() -> TempDir {
+    let project = common::create_temp_dir();
+    let root = project.path();
+    fs::write(root.join(".env"), "API_KEY=hunter2\n").unwrap();
+    fs::write(root.join(".npmrc"), "//registry.npmjs.org/:_authToken=x\n").unwrap();
+    fs::write(root.join("README.md"), "# project\n").unwrap();
+    fs::create_dir_all(root.join("config")).unwrap();
+    fs::write(root.join("config/server.pem"), "-----BEGIN-----\n").unwrap();
+    fs::create_dir_all(root.join("node_modules/pkg")).unwrap();
+    fs::write(root.join("node_modules/pkg/fixture.pem"), "").unwrap();
+    project
+}
+
+#[test]
+fn test_mask_secrets_makes_secret_files_inaccessible() {
+    let project = project_with_secrets();
+    let root = project.path().display().to_string();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.current_dir(project.path());
+    cmd.args(["--mask-secrets", "--dry-run", "--", "echo", "hi"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "-pInaccessiblePaths={}/.env",
+            root
+        )))
+        .stdout(predicate::str::contains(format!(
+            "-pInaccessiblePaths={}/.npmrc",
+            root
+        )))
+        .stdout(predicate::str::contains(format!(
+            "-pInaccessiblePaths={}/config/server.pem",
+            root
+        )))
+        .stdout(predicate::str::contains("README.md").not())
+        .stdout(predicate::str::contains("node_modules").not());
+}
+
+#[test]
+fn test_secrets_not_masked_by_default() {
+    let project = project_with_secrets();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.current_dir(project.path());
+    cmd.args(["--dry-run", "--", "echo", "hi"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("InaccessiblePaths").not());
+}
+
+#[test]
+fn test_coding_agent_masks_secrets() {
+    let project = project_with_secrets();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.current_dir(project.path());
+    cmd.args(["--profile", "coding-agent", "--dry-run", "--", "claude"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("/.env"));
+}
+
+#[test]
+fn test_mask_secrets_can_be_turned_off() {
+    let project = project_with_secrets();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.current_dir(project.path());
+    cmd.args([
+        "--profile",
+        "coding-agent",
+        "--mask-secrets=false",
+        "--dry-run",
+        "--",
+        "claude",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("InaccessiblePaths").not());
+}
+
+#[test]
+fn test_extra_secret_pattern() {
+    let project = project_with_secrets();
+    fs::write(project.path().join("deploy.secret"), "").unwrap();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.current_dir(project.path());
+    cmd.args([
+        "--mask-secrets",
+        "--secret-pattern",
+        "*.secret",
+        "--dry-run",
+        "--",
+        "echo",
+        "hi",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("/deploy.secret"))
+        .stdout(predicate::str::contains("/.env"));
+}
+
+#[test]
+fn test_project_file_secret_patterns() {
+    let project = project_with_secrets();
+    fs::write(project.path().join("vault.txt"), "").unwrap();
+    fs::write(
+        project.path().join(".playpen.toml"),
+        "mask_secrets = true\nsecret_patterns = [\"vault.*\"]\n",
+    )
+    .unwrap();
+    let config = common::trust_project_file(project.path());
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.current_dir(project.path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args(["--dry-run", "--", "echo", "hi"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("/vault.txt"))
+        .stdout(predicate::str::contains("/.npmrc"));
+}
+
+#[test]
+fn test_masked_files_explained() {
+    let project = project_with_secrets();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.current_dir(project.path());
+    cmd.args(["--profile", "coding-agent", "--explain", "--", "claude"]);
+
+    cmd.assert().success().stdout(
+        predicate::str::is_match(r"InaccessiblePaths=\S+/\.env\s+profile:coding-agent").unwrap(),
+    );
+}
+
+// ============ Patterns ============
+
+#[test]
+fn test_wildcard_only_secret_pattern_rejected() {
+    for pattern in ["*", "?*"] {
+        let mut cmd = Command::new(common::get_playpen_path());
+        cmd.args(["--mask-secrets", "--secret-pattern", pattern]);
+        cmd.args(["--dry-run", "--", "echo", "hi"]);
+
+        cmd.assert().failure().stderr(predicate::str::contains(
+            "a pattern of only wildcards would mask the whole project",
+        ));
+    }
+}
+
+#[test]
+fn test_empty_secret_pattern_rejected() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["--mask-secrets", "--secret-pattern", ""]);
+    cmd.args(["--dry-run", "--", "echo", "hi"]);
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid secret pattern: empty"));
+}
+
+#[test]
+fn test_profile_secret_pattern_checked() {
+    let config = common::create_config_dir(
+        "[[profile]]\nname = \"vault\"\nsecret_patterns = [\"config/*.pem\"]\n",
+    );
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args(["--profile", "vault", "--dry-run", "--", "echo", "hi"]);
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("profile vault"))
+        .stderr(predicate::str::contains(
+            "bad secret_patterns: invalid secret pattern: config/*.pem",
+        ));
+}
+
+// ============ What the search leaves out ============
+
+#[test]
+fn test_unsearched_dirs_explained() {
+    let project = project_with_secrets();
+    let mut deep = project.path().to_path_buf();
+    for level in ["a", "b", "c", "d", "e", "f", "g"] {
+        deep.push(level);
+    }
+    fs::create_dir_all(&deep).unwrap();
+    let root = project.path().display().to_string();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.current_dir(project.path());
+    cmd.args(["--mask-secrets", "--explain", "--", "echo", "hi"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Not searched for secrets:"))
+        .stdout(
+            predicate::str::is_match(format!(
+                r"(?m)^\s+{}/a/b/c/d/e/f/g\s+more than 6 levels down$",
+                common::regex_escape(&root)
+            ))
+            .unwrap(),
+        )
+        .stdout(
+            predicate::str::is_match(format!(
+                r"(?m)^\s+{}/node_modules\s+skipped directory$",
+                common::regex_escape(&root)
+            ))
+            .unwrap(),
+        );
+}
+
+#[test]
+fn test_unsearched_dirs_in_dry_run_json() {
+    let project = project_with_secrets();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.current_dir(project.path());
+    cmd.args(["--mask-secrets", "--dry-run=json", "--", "echo", "hi"]);
+
+    let output = cmd.output().unwrap();
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let unsearched = report["unsearched_dirs"].as_array().unwrap();
+    assert!(unsearched.iter().any(|u| {
+        u["path"].as_str().unwrap().ends_with("/node_modules") && u["reason"] == "skipped"
+    }));
+}
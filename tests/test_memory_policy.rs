@@ -0,0 +1,284 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+mod common;
+
+#[test]
+fn test_memory_high_percentage_of_limit() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "-m",
+        "2G",
+        "--memory-high",
+        "90%",
+        "--dry-run",
+        "--",
+        "cargo",
+        "build",
+    ]);
+
+    // 90% of 2G, rounded down to a whole kilobyte.
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pMemoryMax=2G"))
+        .stdout(predicate::str::contains("-pMemoryHigh=1887436K"));
+}
+
+#[test]
+fn test_memory_high_and_low_sizes_passed_through() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "-m",
+        "2G",
+        "--memory-high",
+        "1500M",
+        "--memory-low",
+        "256M",
+        "--dry-run",
+        "--",
+        "cargo",
+        "build",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pMemoryHigh=1500M"))
+        .stdout(predicate::str::contains("-pMemoryLow=256M"));
+}
+
+#[test]
+fn test_memory_low_percentage_of_limit() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "-m",
+        "1G",
+        "--memory-low",
+        "25%",
+        "--dry-run",
+        "--",
+        "cargo",
+        "build",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pMemoryLow=256M"));
+}
+
+#[test]
+fn test_percentage_of_percentage_limit() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "-m",
+        "50%",
+        "--memory-high",
+        "90%",
+        "--dry-run",
+        "--",
+        "cargo",
+        "build",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pMemoryHigh=45%"));
+}
+
+#[test]
+fn test_percentage_without_limit_rejected() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["--memory-high", "80%", "--dry-run", "--", "cargo", "build"]);
+
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--memory-high 80% is a share of the memory limit; set one with -m/--memory-limit",
+    ));
+}
+
+#[test]
+fn test_percentage_with_infinite_limit_rejected() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "-m",
+        "infinity",
+        "--memory-low",
+        "25%",
+        "--dry-run",
+        "--",
+        "cargo",
+        "build",
+    ]);
+
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--memory-low 25% is a share of the memory limit",
+    ));
+}
+
+#[test]
+fn test_size_without_limit_accepted() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["--memory-high", "1G", "--dry-run", "--", "cargo", "build"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pMemoryHigh=1G"));
+}
+
+#[test]
+fn test_policy_without_limit_rejected() {
+    for policy in ["soft", "swap"] {
+        let mut cmd = Command::new(common::get_playpen_path());
+        cmd.args([
+            "--memory-policy",
+            policy,
+            "--dry-run",
+            "--",
+            "cargo",
+            "build",
+        ]);
+
+        cmd.assert()
+            .failure()
+            .stderr(predicate::str::contains(format!(
+                "--memory-policy {} needs a memory limit; set one with -m/--memory-limit",
+                policy
+            )));
+    }
+}
+
+#[test]
+fn test_hard_policy_without_limit_accepted() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--memory-policy",
+        "hard",
+        "--dry-run",
+        "--",
+        "cargo",
+        "build",
+    ]);
+
+    cmd.assert().success();
+}
+
+#[test]
+fn test_percentage_resolved_against_profile_limit() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--profile",
+        "npm",
+        "--memory-high",
+        "50%",
+        "--dry-run",
+        "--",
+        "npm",
+        "test",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pMemoryHigh=512M"));
+}
+
+#[test]
+fn test_soft_policy_sets_memory_high() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--profile",
+        "cargo",
+        "--memory-policy",
+        "soft",
+        "--dry-run",
+        "--",
+        "cargo",
+        "build",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pMemoryHigh=1887436K"))
+        .stdout(predicate::str::contains("-pMemorySwapMax=0"));
+}
+
+#[test]
+fn test_swap_policy_allows_swap_up_to_limit() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--profile",
+        "cargo",
+        "--memory-policy",
+        "swap",
+        "--dry-run",
+        "--",
+        "cargo",
+        "build",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pMemoryHigh=1887436K"))
+        .stdout(predicate::str::contains("-pMemorySwapMax=2G"))
+        .stdout(predicate::str::contains("-pMemorySwapMax=0").not());
+}
+
+#[test]
+fn test_hard_policy_sets_no_memory_high() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "-m",
+        "2G",
+        "--memory-policy",
+        "hard",
+        "--dry-run",
+        "--",
+        "cargo",
+        "build",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pMemoryMax=2G"))
+        .stdout(predicate::str::contains("MemoryHigh").not());
+}
+
+#[test]
+fn test_explicit_flags_override_policy() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "-m",
+        "2G",
+        "--memory-high",
+        "1G",
+        "--memory-swap-max",
+        "512M",
+        "--memory-policy",
+        "swap",
+        "--dry-run",
+        "--",
+        "cargo",
+        "build",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pMemoryHigh=1G"))
+        .stdout(predicate::str::contains("-pMemorySwapMax=512M"));
+}
+
+#[test]
+fn test_memory_policy_in_explain() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "-m",
+        "2G",
+        "--memory-policy",
+        "soft",
+        "--explain",
+        "--",
+        "cargo",
+        "build",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(r"MemoryHigh=1887436K\s+cli").unwrap());
+}
@@ -7,7 +7,7 @@ mod common;
 
 fn npm_config_get(field: &str) -> Option<String> {
     let output = Command::new("npm")
-        .args(&["config", "get", field])
+        .args(["config", "get", field])
         .output()
         .ok()?;
 
@@ -48,7 +48,7 @@ fn test_npm_project_without_protection() {
     // Run npm script without protection - should be able to access home
     let output = Command::new(common::get_playpen_path())
         .current_dir(temp_dir.path())
-        .args(&[
+        .args([
             "--protect-home=none",
             "--ro",
             "/run",
@@ -102,7 +102,7 @@ fn test_npm_project_with_current_dir_only() {
     // Add specific paths needed for npm/node to function
     let output = Command::new(common::get_playpen_path())
         .current_dir(temp_dir.path())
-        .args(&[
+        .args([
             "--current-dir-only",
             "--ro",
             "/home/caleb/.local", // Node installation via fnm
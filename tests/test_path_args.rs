@@ -0,0 +1,111 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+mod common;
+
+// How `--rw`, `--ro` and `--inaccessible` values are expanded and resolved
+// before they become properties.
+
+#[test]
+fn test_relative_path_resolved_against_cwd() {
+    let dir = common::create_temp_dir();
+    std::fs::create_dir(dir.path().join("build")).unwrap();
+    let root = std::fs::canonicalize(dir.path()).unwrap();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.current_dir(dir.path());
+    cmd.args(["--rw", "./build", "--dry-run", "--", "echo", "hi"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "-pBindPaths={}/build",
+            root.display()
+        )));
+}
+
+#[test]
+fn test_tilde_and_variables_expanded() {
+    let home = common::create_temp_dir();
+    std::fs::create_dir(home.path().join(".cargo")).unwrap();
+    let home_path = std::fs::canonicalize(home.path()).unwrap();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("HOME", &home_path);
+    cmd.env("PLAYPEN_DIR", &home_path);
+    cmd.args([
+        "--ro",
+        "~/.cargo",
+        "--inaccessible",
+        "$PLAYPEN_DIR/.cargo/../.cargo",
+        "--dry-run",
+        "--",
+        "echo",
+        "hi",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "-pBindReadOnlyPaths={}/.cargo",
+            home_path.display()
+        )))
+        .stdout(predicate::str::contains(format!(
+            "-pInaccessiblePaths={}/.cargo",
+            home_path.display()
+        )))
+        .stdout(predicate::str::contains("~").not());
+}
+
+#[test]
+fn test_missing_path_is_an_error() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["--ro", "/nonexistent/playpen", "--dry-run", "--", "true"]);
+
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "no such path: /nonexistent/playpen (prefix it with - to skip it when missing)",
+    ));
+}
+
+#[test]
+fn test_optional_missing_path_keeps_prefix() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--inaccessible",
+        "-/nonexistent/playpen",
+        "--dry-run",
+        "--",
+        "true",
+    ]);
+
+    cmd.assert().success().stdout(predicate::str::contains(
+        "-pInaccessiblePaths=-/nonexistent/playpen",
+    ));
+}
+
+#[test]
+fn test_bind_destination_kept() {
+    let dir = common::create_temp_dir();
+    let root = std::fs::canonicalize(dir.path()).unwrap();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.current_dir(dir.path());
+    cmd.args(["--ro", ".:/work:norbind", "--dry-run", "--", "true"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "-pBindReadOnlyPaths={}:/work:norbind",
+            root.display()
+        )));
+}
+
+#[test]
+fn test_flag_not_taken_as_path() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["--rw", "--dry-run", "--", "true"]);
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("expected a path, found --dry-run"));
+}
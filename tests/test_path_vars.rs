@@ -0,0 +1,198 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::os::unix::fs::MetadataExt;
+
+mod common;
+
+// Variables playpen computes for paths whether or not the environment
+// exports them: $UID, $CWD, $PROJECT_ROOT and the XDG base directories.
+
+/// A `playpen` command with `home` as `$HOME` and none of the variables
+/// under test exported.
+fn playpen_with_home(home: &std::path::Path) -> Command {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("HOME", home);
+    for name in [
+        "UID",
+        "XDG_RUNTIME_DIR",
+        "XDG_CACHE_HOME",
+        "XDG_DATA_HOME",
+        "XDG_STATE_HOME",
+    ] {
+        cmd.env_remove(name);
+    }
+    cmd
+}
+
+#[test]
+fn test_uid_and_xdg_defaults_in_builtin_profile() {
+    let home = common::create_temp_dir();
+    let uid = std::fs::metadata(home.path()).unwrap().uid();
+
+    let mut cmd = playpen_with_home(home.path());
+    cmd.args(["profiles", "show", "npm"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "/run/user/{}/fnm_multishells",
+            uid
+        )))
+        .stdout(predicate::str::contains(format!(
+            "{}/.cache/yarn",
+            home.path().display()
+        )))
+        .stdout(predicate::str::contains(format!(
+            "{}/.local/share/pnpm",
+            home.path().display()
+        )))
+        .stdout(predicate::str::contains("$").not());
+}
+
+#[test]
+fn test_xdg_dir_from_environment_when_absolute() {
+    let home = common::create_temp_dir();
+
+    let mut cmd = playpen_with_home(home.path());
+    cmd.env("XDG_CACHE_HOME", "/var/cache/me");
+    cmd.env("XDG_DATA_HOME", "relative/share");
+    cmd.args(["profiles", "show", "uv"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("/var/cache/me/uv"))
+        .stdout(predicate::str::contains(format!(
+            "{}/.local/share/uv",
+            home.path().display()
+        )))
+        .stdout(predicate::str::contains("relative/share").not());
+}
+
+#[test]
+fn test_project_root_is_git_root() {
+    let project = common::create_temp_dir();
+    let root = std::fs::canonicalize(project.path()).unwrap();
+    std::fs::create_dir(root.join(".git")).unwrap();
+    std::fs::create_dir_all(root.join("tools")).unwrap();
+    std::fs::create_dir_all(root.join("src/deep")).unwrap();
+    let config = common::create_config_dir(
+        "[[profile]]\nname = \"tools\"\nproject_root = \"auto\"\nro_paths = [\"$PROJECT_ROOT/tools\"]\n",
+    );
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.current_dir(root.join("src/deep"));
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args(["--profile", "tools", "--dry-run", "--", "true"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "-pBindReadOnlyPaths={}/tools",
+            root.display()
+        )));
+}
+
+#[test]
+fn test_project_root_in_project_file() {
+    let project = common::create_temp_dir();
+    let root = std::fs::canonicalize(project.path()).unwrap();
+    std::fs::create_dir(root.join(".git")).unwrap();
+    std::fs::create_dir(root.join("out")).unwrap();
+    std::fs::create_dir(root.join("sub")).unwrap();
+    std::fs::write(
+        root.join("sub/.playpen.toml"),
+        "project_root = \"git\"\nrw_paths = [\"$PROJECT_ROOT/out\"]\n",
+    )
+    .unwrap();
+    let config = common::trust_project_file(&root.join("sub"));
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.current_dir(root.join("sub"));
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args(["--dry-run", "--", "true"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "-pBindPaths={}/out",
+            root.display()
+        )));
+}
+
+#[test]
+fn test_cwd_in_path_flag() {
+    let dir = common::create_temp_dir();
+    let cwd = std::fs::canonicalize(dir.path()).unwrap();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.current_dir(&cwd);
+    cmd.env_remove("CWD");
+    cmd.args(["--inaccessible", "$CWD", "--dry-run", "--", "true"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "-pInaccessiblePaths={}",
+            cwd.display()
+        )));
+}
+
+#[test]
+fn test_npm_profile_keeps_runtime_dir_sockets_hidden() {
+    let home = common::create_temp_dir();
+    let uid = std::fs::metadata(home.path()).unwrap().uid();
+
+    let mut cmd = playpen_with_home(home.path());
+    cmd.args(["profiles", "show", "npm"]);
+
+    cmd.assert().success().stdout(
+        predicate::str::is_match(format!(r"ro_paths\s+/run/user/{}[ \n]", uid))
+            .unwrap()
+            .not(),
+    );
+}
+
+#[test]
+fn test_project_root_follows_project_root_flag() {
+    // `$PROJECT_ROOT` is the directory --project-root picks, not the
+    // enclosing checkout.
+    let project = common::create_temp_dir();
+    let root = std::fs::canonicalize(project.path()).unwrap();
+    std::fs::create_dir(root.join(".git")).unwrap();
+    std::fs::create_dir_all(root.join("app/out")).unwrap();
+    let app = root.join("app").display().to_string();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.current_dir(&root);
+    cmd.args(["--project-root", &app, "--rw", "$PROJECT_ROOT/out"]);
+    cmd.args(["--dry-run", "--", "true"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!("-pBindPaths={}/out", app)));
+}
+
+#[test]
+fn test_project_root_is_cwd_for_checkout_at_home() {
+    // `auto` never picks a home directory that is itself a checkout, and
+    // neither does `$PROJECT_ROOT`.
+    let home = common::create_temp_dir();
+    let home_dir = std::fs::canonicalize(home.path()).unwrap();
+    std::fs::create_dir(home_dir.join(".git")).unwrap();
+    std::fs::create_dir_all(home_dir.join("work/out")).unwrap();
+    let config = common::create_config_dir(
+        "[[profile]]\nname = \"tools\"\nproject_root = \"auto\"\nrw_paths = [\"$PROJECT_ROOT/out\"]\n",
+    );
+
+    let mut cmd = playpen_with_home(&home_dir);
+    cmd.current_dir(home_dir.join("work"));
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args(["--profile", "tools", "--dry-run", "--", "true"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "-pBindPaths={}/work/out",
+            home_dir.display()
+        )));
+}
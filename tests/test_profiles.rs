@@ -13,6 +13,7 @@ fn test_cargo_profile_dry_run() {
         .stdout(predicate::str::contains("-pMemoryMax=2G"))
         .stdout(predicate::str::contains("-pMemorySwapMax=0"))
         .stdout(predicate::str::contains("-pCPUQuota=300%"))
+        .stdout(predicate::str::contains("-pTasksMax=4096"))
         .stdout(predicate::str::contains("-pProtectHome=tmpfs"))
         .stdout(predicate::str::contains("-pBindPaths=").and(predicate::str::contains("/.cargo")))
         .stdout(
@@ -92,6 +93,11 @@ fn test_coding_agent_profile_dry_run() {
         .success()
         .stdout(predicate::str::contains("-pMemoryMax=4G"))
         .stdout(predicate::str::contains("-pCPUQuota=200%"))
+        .stdout(predicate::str::contains("-pTasksMax=1024"))
+        // Low priority, so the agent does not starve the user's editor.
+        .stdout(predicate::str::contains("-pCPUWeight=20"))
+        .stdout(predicate::str::contains("-pNice=10"))
+        .stdout(predicate::str::contains("-pIOSchedulingClass=idle"))
         .stdout(predicate::str::contains("-pProtectHome=tmpfs"))
         .stdout(
             predicate::str::contains("-pBindReadOnlyPaths=")
@@ -162,6 +168,178 @@ fn test_memory_swap_max_override() {
         .stdout(predicate::str::contains("-pMemorySwapMax=0"));
 }
 
+#[test]
+fn test_tasks_max_override() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--profile",
+        "cargo",
+        "--tasks-max",
+        "256",
+        "--dry-run",
+        "--",
+        "echo",
+        "hello",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pTasksMax=256"))
+        .stdout(predicate::str::contains("-pTasksMax=4096").not());
+}
+
+#[test]
+fn test_tasks_max_percentage_and_infinity() {
+    for value in ["10%", "infinity"] {
+        let mut cmd = Command::new(common::get_playpen_path());
+        cmd.args(["--tasks-max", value, "--dry-run", "--", "echo", "hello"]);
+
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains(format!("-pTasksMax={}", value)));
+    }
+}
+
+#[test]
+fn test_tasks_max_invalid_rejected() {
+    for value in ["0", "lots", "150%"] {
+        let mut cmd = Command::new(common::get_playpen_path());
+        cmd.args(["--tasks-max", value, "--dry-run", "--", "echo", "hello"]);
+
+        cmd.assert()
+            .failure()
+            .stderr(predicate::str::contains("invalid task limit"));
+    }
+}
+
+#[test]
+fn test_every_builtin_profile_limits_tasks() {
+    for profile in [
+        "cargo",
+        "npm",
+        "pytest",
+        "python",
+        "uv",
+        "go",
+        "make",
+        "coding-agent",
+        "shell",
+    ] {
+        let mut cmd = Command::new(common::get_playpen_path());
+        cmd.args(["--profile", profile, "--dry-run", "--", "echo", "hello"]);
+
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::is_match(r"-pTasksMax=\d+").unwrap());
+    }
+}
+
+#[test]
+fn test_no_profile_no_tasks_max() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["--dry-run", "--", "echo", "hello"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("TasksMax").not());
+}
+
+#[test]
+fn test_cpu_pinning_and_priority_flags() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--allowed-cpus",
+        "0-3,8",
+        "--allowed-memory-nodes",
+        "0",
+        "--cpu-weight",
+        "50",
+        "--nice",
+        "5",
+        "--io-scheduling-class",
+        "best-effort",
+        "--dry-run",
+        "--",
+        "./bench",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pAllowedCPUs=0-3,8"))
+        .stdout(predicate::str::contains("-pAllowedMemoryNodes=0"))
+        .stdout(predicate::str::contains("-pCPUWeight=50"))
+        .stdout(predicate::str::contains("-pNice=5"))
+        .stdout(predicate::str::contains("-pIOSchedulingClass=best-effort"));
+}
+
+#[test]
+fn test_negative_nice_accepted() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["--nice", "-5", "--dry-run", "--", "echo", "hello"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pNice=-5"));
+}
+
+#[test]
+fn test_priority_flags_override_coding_agent_profile() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--profile",
+        "coding-agent",
+        "--nice",
+        "0",
+        "--cpu-weight",
+        "100",
+        "--io-scheduling-class",
+        "best-effort",
+        "--dry-run",
+        "--",
+        "claude",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pNice=0"))
+        .stdout(predicate::str::contains("-pCPUWeight=100"))
+        .stdout(predicate::str::contains("-pIOSchedulingClass=best-effort"))
+        .stdout(predicate::str::contains("=idle").not());
+}
+
+#[test]
+fn test_invalid_cpu_settings_rejected() {
+    for args in [
+        ["--allowed-cpus", "3-1"],
+        ["--allowed-cpus", "all"],
+        ["--cpu-weight", "0"],
+        ["--cpu-weight", "20000"],
+        ["--nice", "20"],
+        ["--io-scheduling-class", "low"],
+    ] {
+        let mut cmd = Command::new(common::get_playpen_path());
+        cmd.args(args);
+        cmd.args(["--dry-run", "--", "echo", "hello"]);
+
+        cmd.assert()
+            .failure()
+            .stderr(predicate::str::contains("invalid value"));
+    }
+}
+
+#[test]
+fn test_no_profile_no_priority_settings() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["--dry-run", "--", "echo", "hello"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("CPUWeight").not())
+        .stdout(predicate::str::contains("Nice").not())
+        .stdout(predicate::str::contains("IOSchedulingClass").not())
+        .stdout(predicate::str::contains("AllowedCPUs").not());
+}
+
 #[test]
 fn test_profile_plus_explicit_rw_accumulates() {
     let mut cmd = Command::new(common::get_playpen_path());
@@ -169,7 +347,7 @@ fn test_profile_plus_explicit_rw_accumulates() {
         "--profile",
         "cargo",
         "--rw",
-        "/tmp/extra",
+        "-/tmp/extra",
         "--dry-run",
         "--",
         "echo",
@@ -260,3 +438,52 @@ fn test_help_contains_profiles() {
         .stdout(predicate::str::contains("--profile"))
         .stdout(predicate::str::contains("cargo").or(predicate::str::contains("Valid profiles")));
 }
+
+#[test]
+fn test_multiple_profiles_later_limits_win() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--profile",
+        "cargo",
+        "--profile",
+        "go",
+        "--dry-run",
+        "--",
+        "echo",
+        "hello",
+    ]);
+
+    let output = cmd.output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // go's 512M replaces cargo's 2G; go has no swap opinion, so cargo's stays.
+    assert!(stdout.contains("-pMemoryMax=512M"));
+    assert!(!stdout.contains("-pMemoryMax=2G"));
+    assert!(stdout.contains("-pMemorySwapMax=0"));
+}
+
+#[test]
+fn test_multiple_profiles_paths_accumulate() {
+    let temp_home = common::create_temp_dir();
+    std::fs::create_dir(temp_home.path().join(".cargo")).unwrap();
+    std::fs::create_dir(temp_home.path().join(".npm")).unwrap();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("HOME", temp_home.path());
+    cmd.args([
+        "--profile",
+        "npm",
+        "--profile",
+        "cargo",
+        "--dry-run",
+        "--",
+        "echo",
+        "hello",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pMemoryMax=2G"))
+        .stdout(predicate::str::contains("/.cargo"))
+        .stdout(predicate::str::contains("/.npm"));
+}
@@ -0,0 +1,132 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+mod common;
+
+// `playpen profiles ...` inspects profiles without running anything.
+
+#[test]
+fn test_profiles_list() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["profiles", "list"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("cargo"))
+        .stdout(predicate::str::contains("Rust/Cargo builds and tests"))
+        .stdout(predicate::str::contains("coding-agent"))
+        .stdout(predicate::str::contains("shell"));
+}
+
+#[test]
+fn test_profiles_list_includes_user_profiles() {
+    let config = common::create_config_dir(
+        "[[profile]]\nname = \"terraform\"\ndescription = \"Terraform plan/apply\"\n",
+    );
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args(["profiles", "list"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("terraform"))
+        .stdout(predicate::str::contains("Terraform plan/apply"));
+}
+
+#[test]
+fn test_profiles_show_expands_and_marks_skipped_paths() {
+    let temp_home = common::create_temp_dir();
+    std::fs::create_dir(temp_home.path().join(".cargo")).unwrap();
+    // ~/.rustup deliberately missing.
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("HOME", temp_home.path());
+    cmd.args(["profiles", "show", "cargo"]);
+
+    let home = temp_home.path().display().to_string();
+    let output = cmd.output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.contains("memory_limit"));
+    assert!(stdout.contains("2G"));
+    assert!(stdout.contains("300%"));
+    let cargo_line = stdout
+        .lines()
+        .find(|l| l.contains(&format!("{}/.cargo", home)))
+        .expect("expanded ~/.cargo path");
+    assert!(!cargo_line.contains("skipped"));
+    let rustup_line = stdout
+        .lines()
+        .find(|l| l.contains(&format!("{}/.rustup", home)))
+        .expect("expanded ~/.rustup path");
+    assert!(rustup_line.contains("skipped"));
+}
+
+#[test]
+fn test_profiles_show_resolves_extends() {
+    let config = common::create_config_dir(
+        "[[profile]]\nname = \"big-cargo\"\nextends = \"cargo\"\nmemory_limit = \"9G\"\n",
+    );
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args(["profiles", "show", "big-cargo"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("9G"))
+        .stdout(predicate::str::contains("300%"))
+        .stdout(predicate::str::contains("/.rustup"));
+}
+
+#[test]
+fn test_profiles_show_unknown() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["profiles", "show", "nonexistent"]);
+
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "error: unknown profile 'nonexistent'",
+        ));
+}
+
+#[test]
+fn test_profiles_diff() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["profiles", "diff", "cargo", "npm"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(r"(?m)^- memory_limit\s+2G$").unwrap())
+        .stdout(predicate::str::is_match(r"(?m)^\+ memory_limit\s+1G$").unwrap())
+        .stdout(predicate::str::is_match(r"(?m)^- rw_paths\s+\S+/\.cargo$").unwrap())
+        .stdout(predicate::str::is_match(r"(?m)^\+ rw_paths\s+\S+/\.npm$").unwrap())
+        // Both profiles use tmpfs, so protect_home is not part of the diff.
+        .stdout(predicate::str::contains("protect_home").not());
+}
+
+#[test]
+fn test_profiles_diff_identical() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["profiles", "diff", "python", "python"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("(no differences)"));
+}
+
+#[test]
+fn test_run_form_still_works() {
+    // Options and a trailing command without a subcommand still run.
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["-m", "1G", "--dry-run", "echo", "hi"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pMemoryMax=1G"))
+        .stdout(predicate::str::ends_with("echo hi\n"));
+}
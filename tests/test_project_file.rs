@@ -0,0 +1,329 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+mod common;
+
+// Each test builds a throwaway project directory containing a `.playpen.toml`,
+// trusts it in a throwaway configuration directory, and runs playpen from
+// inside it (or a subdirectory of it) with --dry-run.
+
+fn project_with(contents: &str) -> (tempfile::TempDir, tempfile::TempDir) {
+    let dir = common::create_temp_dir();
+    std::fs::write(dir.path().join(".playpen.toml"), contents).unwrap();
+    let config = common::trust_project_file(dir.path());
+    (dir, config)
+}
+
+#[test]
+fn test_project_file_sets_limits() {
+    let (project, config) = project_with("memory_limit = \"3G\"\ncpu_quota = \"150%\"\n");
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.current_dir(project.path());
+    cmd.args(["--dry-run", "--", "cargo", "test"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pMemoryMax=3G"))
+        .stdout(predicate::str::contains("-pCPUQuota=150%"));
+}
+
+#[test]
+fn test_project_file_found_from_subdirectory() {
+    let (project, config) = project_with("memory_limit = \"3G\"\n");
+    let sub = project.path().join("crates/foo");
+    std::fs::create_dir_all(&sub).unwrap();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.current_dir(&sub);
+    cmd.args(["--dry-run", "--", "cargo", "test"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pMemoryMax=3G"));
+}
+
+#[test]
+fn test_project_file_overrides_profile() {
+    let (project, config) = project_with("memory_limit = \"6G\"\n");
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.current_dir(project.path());
+    cmd.args(["--profile", "cargo", "--dry-run", "--", "cargo", "test"]);
+
+    // The project beats the profile's 2G, but the rest of the profile stays.
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pMemoryMax=6G"))
+        .stdout(predicate::str::contains("-pCPUQuota=300%"));
+}
+
+#[test]
+fn test_cli_flag_overrides_project_file() {
+    let (project, config) = project_with("memory_limit = \"6G\"\n");
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.current_dir(project.path());
+    cmd.args(["-m", "1G", "--dry-run", "--", "cargo", "test"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pMemoryMax=1G"))
+        .stdout(predicate::str::contains("-pMemoryMax=6G").not());
+}
+
+#[test]
+fn test_project_file_selects_profile() {
+    let (project, config) = project_with("profile = \"go\"\n");
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.current_dir(project.path());
+    cmd.args(["--dry-run", "--", "go", "test"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pMemoryMax=512M"));
+
+    // An explicit --profile wins over the project's choice.
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.current_dir(project.path());
+    cmd.args(["--profile", "cargo", "--dry-run", "--", "go", "test"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pMemoryMax=2G"));
+}
+
+#[test]
+fn test_project_file_paths_relative_to_file() {
+    let (project, config) = project_with(
+        "rw_paths = [\"target\"]\nro_paths = [\"vendor\"]\ninaccessible_paths = [\".env\"]\n",
+    );
+    std::fs::create_dir(project.path().join("target")).unwrap();
+    std::fs::create_dir(project.path().join("vendor")).unwrap();
+    std::fs::write(project.path().join(".env"), "SECRET=1\n").unwrap();
+    let sub = project.path().join("src");
+    std::fs::create_dir(&sub).unwrap();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.current_dir(&sub);
+    cmd.args(["--dry-run", "--", "make"]);
+
+    let root = project.path().display().to_string();
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "-pBindPaths={}/target",
+            root
+        )))
+        .stdout(predicate::str::contains(format!(
+            "-pBindReadOnlyPaths={}/vendor",
+            root
+        )))
+        .stdout(predicate::str::contains(format!(
+            "-pInaccessiblePaths={}/.env",
+            root
+        )));
+}
+
+#[test]
+fn test_project_file_network_rules_accumulate() {
+    let (project, config) = project_with("ip_deny = [\"any\"]\nip_allow = [\"localhost\"]\n");
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.current_dir(project.path());
+    cmd.args(["--ip-allow", "10.0.0.1", "--dry-run", "--", "true"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pIPAddressDeny=any"))
+        .stdout(predicate::str::contains("-pIPAddressAllow=localhost"))
+        .stdout(predicate::str::contains("-pIPAddressAllow=10.0.0.1"));
+}
+
+#[test]
+fn test_no_project_file_flag() {
+    let (project, config) = project_with("memory_limit = \"3G\"\n");
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.current_dir(project.path());
+    cmd.args(["--no-project-file", "--dry-run", "--", "true"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("MemoryMax").not());
+}
+
+#[test]
+fn test_invalid_project_file_reports_path() {
+    // `playpen trust` checks the file before approving it.
+    let project = common::create_temp_dir();
+    std::fs::write(project.path().join(".playpen.toml"), "memory = \"3G\"\n").unwrap();
+    let config = common::create_temp_dir();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.current_dir(project.path());
+    cmd.arg("trust");
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains(".playpen.toml"));
+}
+
+#[test]
+fn test_project_file_disk_limits() {
+    let (project, config) = project_with("disk_write = \"20M\"\n");
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.current_dir(project.path());
+    cmd.args(["--dry-run", "--", "cargo", "test"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(r"-pIOWriteBandwidthMax=\S+ 20M").unwrap())
+        .stdout(predicate::str::contains("IOReadBandwidthMax").not());
+}
+
+#[test]
+fn test_project_file_sets_priority() {
+    let (project, config) = project_with("nice = 5\ncpu_weight = \"50\"\nallowed_cpus = \"0-1\"\n");
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.current_dir(project.path());
+    cmd.args(["--dry-run", "--", "cargo", "test"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pNice=5"))
+        .stdout(predicate::str::contains("-pCPUWeight=50"))
+        .stdout(predicate::str::contains("-pAllowedCPUs=0-1"));
+}
+
+#[test]
+fn test_untrusted_project_file_ignored() {
+    let project = common::create_temp_dir();
+    std::fs::write(
+        project.path().join(".playpen.toml"),
+        "protect_home = \"none\"\n",
+    )
+    .unwrap();
+    let config = common::create_temp_dir();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.current_dir(project.path());
+    cmd.args(["--profile", "shell", "--dry-run", "--", "true"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("ProtectHome=read-only"))
+        .stderr(predicate::str::contains(
+            ".playpen.toml, which is not trusted",
+        ))
+        .stderr(predicate::str::contains("playpen trust"));
+}
+
+#[test]
+fn test_trust_rejects_invalid_project_file() {
+    let project = common::create_temp_dir();
+    std::fs::write(
+        project.path().join(".playpen.toml"),
+        "memory_limt = \"3G\"\n",
+    )
+    .unwrap();
+    let config = common::create_temp_dir();
+
+    let mut trust = Command::new(common::get_playpen_path());
+    trust.env("XDG_CONFIG_HOME", config.path());
+    trust.current_dir(project.path());
+    trust.arg("trust");
+
+    trust
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("failed to parse"));
+    assert!(!config.path().join("playpen/trusted.toml").exists());
+}
+
+#[test]
+fn test_edited_project_file_needs_trust_again() {
+    let (project, config) = project_with("memory_limit = \"3G\"\n");
+    std::fs::write(
+        project.path().join(".playpen.toml"),
+        "memory_limit = \"3G\"\nrw_paths = [\"/\"]\n",
+    )
+    .unwrap();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.current_dir(project.path());
+    cmd.args(["--dry-run", "--", "true"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("MemoryMax").not())
+        .stderr(predicate::str::contains("is not trusted"));
+
+    let mut trust = Command::new(common::get_playpen_path());
+    trust.env("XDG_CONFIG_HOME", config.path());
+    trust.current_dir(project.path());
+    trust.arg("trust");
+    trust
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Trusted "));
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.current_dir(project.path());
+    cmd.args(["--dry-run", "--", "true"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pBindPaths=/ "));
+}
+
+#[test]
+fn test_project_file_bound_read_only() {
+    let (project, config) = project_with("memory_limit = \"3G\"\n");
+    let file = std::fs::canonicalize(project.path())
+        .unwrap()
+        .join(".playpen.toml");
+    let store = std::fs::canonicalize(config.path())
+        .unwrap()
+        .join("playpen/trusted.toml");
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.current_dir(project.path());
+    // The shell profile hides /tmp, so the record is only reachable through
+    // the --rw bind.
+    cmd.args([
+        "--profile",
+        "shell",
+        "--rw",
+        config.path().to_str().unwrap(),
+    ]);
+    cmd.args(["--dry-run", "--", "true"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "-pBindReadOnlyPaths={}",
+            file.display()
+        )))
+        .stdout(predicate::str::contains(format!(
+            "-pBindReadOnlyPaths={}",
+            store.display()
+        )));
+}
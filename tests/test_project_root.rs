@@ -0,0 +1,221 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::path::{Path, PathBuf};
+
+mod common;
+
+/// A git checkout with a member crate two levels down, returned with its
+/// resolved root.
+fn workspace() -> (tempfile::TempDir, PathBuf) {
+    let dir = common::create_temp_dir();
+    let root = std::fs::canonicalize(dir.path()).unwrap();
+    std::fs::create_dir(root.join(".git")).unwrap();
+    std::fs::create_dir_all(root.join("crates/foo")).unwrap();
+    (dir, root)
+}
+
+fn playpen_in(dir: &Path) -> Command {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.current_dir(dir);
+    cmd
+}
+
+#[test]
+fn test_cargo_profile_binds_git_root() {
+    let (_dir, root) = workspace();
+
+    let mut cmd = playpen_in(&root.join("crates/foo"));
+    cmd.args(["--profile", "cargo", "--dry-run", "--", "cargo", "test"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "-pBindPaths={} ",
+            root.display()
+        )))
+        .stdout(predicate::str::contains("crates/foo").not());
+}
+
+#[test]
+fn test_project_root_cwd_overrides_profile() {
+    let (_dir, root) = workspace();
+    let member = root.join("crates/foo");
+
+    let mut cmd = playpen_in(&member);
+    cmd.args([
+        "--profile",
+        "cargo",
+        "--project-root",
+        "cwd",
+        "--dry-run",
+        "--",
+        "cargo",
+        "test",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "-pBindPaths={}",
+            member.display()
+        )));
+}
+
+#[test]
+fn test_project_root_path() {
+    let (_dir, root) = workspace();
+
+    let mut cmd = playpen_in(&root.join("crates/foo"));
+    cmd.args([
+        "--profile",
+        "shell",
+        "--project-root",
+        "../..",
+        "--dry-run",
+        "--",
+        "true",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "-pBindPaths={} ",
+            root.display()
+        )));
+}
+
+#[test]
+fn test_project_root_not_a_directory() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--project-root",
+        "/nonexistent/playpen",
+        "--dry-run",
+        "--",
+        "true",
+    ]);
+
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "not a directory: /nonexistent/playpen (expected auto, git, cwd or a directory)",
+    ));
+}
+
+#[test]
+fn test_project_root_git_outside_repository() {
+    let dir = common::create_temp_dir();
+
+    let mut cmd = playpen_in(dir.path());
+    cmd.args([
+        "--profile",
+        "cargo",
+        "--project-root",
+        "git",
+        "--dry-run",
+        "--",
+        "true",
+    ]);
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("is not inside a git repository"));
+}
+
+#[test]
+fn test_auto_ignores_home_repository() {
+    let home = common::create_temp_dir();
+    let home_path = std::fs::canonicalize(home.path()).unwrap();
+    std::fs::create_dir(home_path.join(".git")).unwrap();
+    std::fs::create_dir(home_path.join("project")).unwrap();
+
+    let mut cmd = playpen_in(&home_path.join("project"));
+    cmd.env("HOME", &home_path);
+    cmd.args(["--profile", "cargo", "--dry-run", "--", "true"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "-pBindPaths={}/project",
+            home_path.display()
+        )));
+}
+
+#[test]
+fn test_worktree_git_dirs_bound() {
+    let dir = common::create_temp_dir();
+    let base = std::fs::canonicalize(dir.path()).unwrap();
+    let git_dir = base.join("main/.git/worktrees/feature");
+    std::fs::create_dir_all(&git_dir).unwrap();
+    std::fs::write(git_dir.join("commondir"), "../..\n").unwrap();
+    std::fs::create_dir_all(base.join("feature/src")).unwrap();
+    std::fs::write(
+        base.join("feature/.git"),
+        format!("gitdir: {}\n", git_dir.display()),
+    )
+    .unwrap();
+
+    let mut cmd = playpen_in(&base.join("feature/src"));
+    cmd.args(["--profile", "cargo", "--dry-run", "--", "true"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "-pBindPaths={}/feature ",
+            base.display()
+        )))
+        .stdout(predicate::str::contains(format!(
+            "-pBindPaths={}/main/.git ",
+            base.display()
+        )))
+        .stdout(predicate::str::contains("worktrees").not());
+}
+
+#[test]
+fn test_project_file_project_root() {
+    let (_dir, root) = workspace();
+    std::fs::write(root.join(".playpen.toml"), "project_root = \"git\"\n").unwrap();
+    let config = common::trust_project_file(&root);
+
+    let mut cmd = playpen_in(&root.join("crates/foo"));
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args(["--profile", "shell", "--dry-run", "--", "true"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "-pBindPaths={} ",
+            root.display()
+        )));
+}
+
+#[test]
+fn test_project_root_explained() {
+    let (_dir, root) = workspace();
+
+    let mut cmd = playpen_in(&root.join("crates/foo"));
+    cmd.args([
+        "--profile",
+        "cargo",
+        "--project-root",
+        "git",
+        "--explain",
+        "--",
+        "true",
+    ]);
+
+    cmd.assert().success().stdout(
+        predicate::str::is_match(r"BindPaths=\S+\s+cli \(overrides profile:cargo=auto\)").unwrap(),
+    );
+}
+
+#[test]
+fn test_project_root_without_bind_not_emitted() {
+    let (_dir, root) = workspace();
+
+    let mut cmd = playpen_in(&root.join("crates/foo"));
+    cmd.args(["--project-root", "git", "--explain", "--", "true"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("BindPaths").not())
+        .stdout(predicate::str::contains("project_root = git"));
+}
@@ -0,0 +1,248 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::path::{Path, PathBuf};
+
+mod common;
+
+/// A checkout laid out as `git init` leaves it, returned with its resolved
+/// root.
+fn repository() -> (tempfile::TempDir, PathBuf) {
+    let dir = common::create_temp_dir();
+    let root = std::fs::canonicalize(dir.path()).unwrap();
+    std::fs::create_dir_all(root.join(".git/hooks")).unwrap();
+    std::fs::create_dir_all(root.join(".git/info")).unwrap();
+    std::fs::create_dir_all(root.join(".git/objects")).unwrap();
+    std::fs::write(root.join(".git/config"), "[core]\n").unwrap();
+    std::fs::create_dir(root.join("src")).unwrap();
+    (dir, root)
+}
+
+/// The `--dry-run` output of playpen run in `dir` with `args`.
+fn dry_run(dir: &Path, args: &[&str]) -> String {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.current_dir(dir);
+    cmd.args(args);
+    cmd.args(["--dry-run", "--", "true"]);
+    let output = cmd.output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_coding_agent_protects_git_metadata() {
+    let (_dir, root) = repository();
+
+    let stdout = dry_run(&root, &["--profile", "coding-agent"]);
+
+    for entry in ["hooks", "config", "info"] {
+        assert!(
+            stdout.contains(&format!(
+                "-pBindReadOnlyPaths={}/.git/{}",
+                root.display(),
+                entry
+            )),
+            "{} not protected: {}",
+            entry,
+            stdout
+        );
+    }
+    assert!(!stdout.contains(".git/objects"));
+}
+
+#[test]
+fn test_protected_paths_follow_project_bind() {
+    let (_dir, root) = repository();
+
+    let stdout = dry_run(&root, &["--profile", "coding-agent"]);
+
+    let project = stdout
+        .find(&format!("-pBindPaths={} ", root.display()))
+        .expect("project bind");
+    let hooks = stdout
+        .find(&format!(
+            "-pBindReadOnlyPaths={}/.git/hooks",
+            root.display()
+        ))
+        .expect("hooks bind");
+    assert!(project < hooks);
+}
+
+#[test]
+fn test_protect_git_can_be_turned_off() {
+    let (_dir, root) = repository();
+
+    let stdout = dry_run(&root, &["--profile", "coding-agent", "--protect-git=false"]);
+
+    assert!(!stdout.contains(".git/"));
+}
+
+#[test]
+fn test_protect_git_off_by_default() {
+    let (_dir, root) = repository();
+
+    let stdout = dry_run(&root, &["--current-dir-only"]);
+
+    assert!(!stdout.contains(".git/"));
+}
+
+#[test]
+fn test_protect_git_flag() {
+    let (_dir, root) = repository();
+
+    let stdout = dry_run(&root, &["--current-dir-only", "--protect-git"]);
+
+    assert!(stdout.contains(&format!(
+        "-pBindReadOnlyPaths={}/.git/config",
+        root.display()
+    )));
+}
+
+#[test]
+fn test_hidden_git_dir_not_exposed() {
+    // From a subdirectory only the subdirectory is bound, and the .git above
+    // it stays hidden under the private /tmp.
+    let (_dir, root) = repository();
+
+    let stdout = dry_run(&root.join("src"), &["--profile", "coding-agent"]);
+
+    assert!(!stdout.contains(".git/"));
+}
+
+#[test]
+fn test_worktree_metadata_protected() {
+    let dir = common::create_temp_dir();
+    let base = std::fs::canonicalize(dir.path()).unwrap();
+    let git_dir = base.join("main/.git/worktrees/feature");
+    std::fs::create_dir_all(&git_dir).unwrap();
+    std::fs::create_dir_all(base.join("main/.git/hooks")).unwrap();
+    std::fs::write(base.join("main/.git/config"), "[core]\n").unwrap();
+    std::fs::write(git_dir.join("commondir"), "../..\n").unwrap();
+    std::fs::write(git_dir.join("gitdir"), "/elsewhere/.git\n").unwrap();
+    std::fs::create_dir(base.join("feature")).unwrap();
+    std::fs::write(
+        base.join("feature/.git"),
+        format!("gitdir: {}\n", git_dir.display()),
+    )
+    .unwrap();
+
+    let stdout = dry_run(
+        &base.join("feature"),
+        &["--profile", "cargo", "--protect-git"],
+    );
+
+    for path in [
+        "feature/.git",
+        "main/.git/hooks",
+        "main/.git/config",
+        "main/.git/worktrees/feature/commondir",
+        "main/.git/worktrees/feature/gitdir",
+    ] {
+        assert!(
+            stdout.contains(&format!("-pBindReadOnlyPaths={}/{}", base.display(), path)),
+            "{} not protected: {}",
+            path,
+            stdout
+        );
+    }
+}
+
+#[test]
+fn test_protected_paths_explained() {
+    let (_dir, root) = repository();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.current_dir(&root);
+    cmd.args(["--profile", "coding-agent", "--explain", "--", "claude"]);
+
+    cmd.assert().success().stdout(
+        predicate::str::is_match(r"BindReadOnlyPaths=\S+/\.git/hooks\s+profile:coding-agent")
+            .unwrap(),
+    );
+}
+
+#[test]
+fn test_git_dir_cannot_be_moved_aside() {
+    // Binding .git onto itself makes it a mount point, so `mv .git .git.old`
+    // fails instead of letting the command put a .git of its own, with its
+    // own hooks, in its place.
+    let (_dir, root) = repository();
+
+    let stdout = dry_run(&root, &["--profile", "coding-agent"]);
+
+    assert!(
+        stdout.contains(&format!("-pBindPaths={}/.git ", root.display())),
+        ".git not bound: {}",
+        stdout
+    );
+}
+
+#[test]
+fn test_git_dir_not_made_writable() {
+    // Under a read-only bind the .git is not writable to begin with; binding
+    // it read-write would change that.
+    let (_dir, root) = repository();
+    let project = root.display().to_string();
+
+    let stdout = dry_run(
+        &root,
+        &["--private-tmp=false", "--ro", &project, "--protect-git"],
+    );
+
+    assert!(!stdout.contains(&format!("-pBindPaths={}/.git", root.display())));
+    assert!(stdout.contains(&format!(
+        "-pBindReadOnlyPaths={}/.git/hooks",
+        root.display()
+    )));
+}
+
+#[test]
+fn test_missing_entries_left_alone() {
+    let (_dir, root) = repository();
+    std::fs::remove_dir(root.join(".git/hooks")).unwrap();
+
+    let stdout = dry_run(&root, &["--current-dir-only", "--protect-git"]);
+    assert!(!stdout.contains("/.git/hooks"));
+
+    // A real run does not create it in the user's repository either, even
+    // though systemd-run cannot be found here.
+    let empty = common::create_temp_dir();
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.current_dir(&root);
+    cmd.env("PATH", empty.path());
+    cmd.args(["--current-dir-only", "--protect-git", "--", "true"]);
+    cmd.assert().failure();
+
+    assert!(!root.join(".git/hooks").exists());
+}
+
+#[test]
+fn test_submodule_metadata_protected() {
+    // Submodule git directories live under .git/modules, by name, and a
+    // submodule's own submodules under its modules in turn.
+    let (_dir, root) = repository();
+    for module in ["lib", "vendor/dep", "lib/modules/nested"] {
+        let git_dir = root.join(".git/modules").join(module);
+        std::fs::create_dir_all(git_dir.join("hooks")).unwrap();
+        std::fs::write(git_dir.join("HEAD"), "ref: refs/heads/main\n").unwrap();
+        std::fs::write(git_dir.join("config"), "[core]\n").unwrap();
+    }
+
+    let stdout = dry_run(&root, &["--profile", "coding-agent"]);
+
+    for module in ["lib", "vendor/dep", "lib/modules/nested"] {
+        let git_dir = format!("{}/.git/modules/{}", root.display(), module);
+        for protected in [
+            format!("-pBindPaths={} ", git_dir),
+            format!("-pBindReadOnlyPaths={}/hooks ", git_dir),
+            format!("-pBindReadOnlyPaths={}/config ", git_dir),
+        ] {
+            assert!(
+                stdout.contains(&protected),
+                "{} missing: {}",
+                protected,
+                stdout
+            );
+        }
+    }
+    assert!(!stdout.contains("/.git/modules/vendor "));
+}
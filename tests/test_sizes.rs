@@ -0,0 +1,178 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+mod common;
+
+#[test]
+fn test_valid_sizes_passed_through() {
+    for value in ["2G", "512M", "1.5G", "1073741824", "infinity", "50%"] {
+        let mut cmd = Command::new(common::get_playpen_path());
+        cmd.args(["-m", value, "--dry-run", "--", "cargo", "build"]);
+
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains(format!("-pMemoryMax={}", value)));
+    }
+}
+
+#[test]
+fn test_byte_suffix_typo_rejected_with_hint() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["-m", "2GB", "--dry-run", "--", "cargo", "build"]);
+
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "invalid size: 2GB (did you mean 2G?)",
+    ));
+}
+
+#[test]
+fn test_invalid_sizes_rejected() {
+    for args in [
+        ["-m", "lots"],
+        ["-m", "2 G"],
+        ["-m", "150%"],
+        ["--memory-swap-max", "1x"],
+        ["--memory-high", "-"],
+        ["--memory-low", "10%rom"],
+    ] {
+        let mut cmd = Command::new(common::get_playpen_path());
+        cmd.args(args);
+        cmd.args(["--dry-run", "--", "cargo", "build"]);
+
+        cmd.assert().failure().stderr(
+            predicate::str::contains("invalid size")
+                .or(predicate::str::contains("invalid percentage")),
+        );
+    }
+}
+
+#[test]
+fn test_invalid_rates_rejected() {
+    for args in [
+        ["-d", "50MB"],
+        ["--disk-read", "50%"],
+        ["--disk-write", "fast"],
+    ] {
+        let mut cmd = Command::new(common::get_playpen_path());
+        cmd.args(args);
+        cmd.args(["--dry-run", "--", "cargo", "build"]);
+
+        cmd.assert()
+            .failure()
+            .stderr(predicate::str::contains("invalid rate"));
+    }
+}
+
+#[test]
+fn test_memory_limit_share_of_ram() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["-m", "25%ram", "--dry-run", "--", "cargo", "build"]);
+
+    // The size depends on the machine; it is written in whole kilobytes or
+    // larger, never as the percentage.
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(r"-pMemoryMax=\d+[KMG] ").unwrap());
+}
+
+#[test]
+fn test_no_share_of_ram_is_zero() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["-m", "0%ram", "--dry-run", "--", "cargo", "build"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pMemoryMax=0 "));
+}
+
+#[test]
+fn test_swap_share_of_ram() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "-m",
+        "1G",
+        "--memory-swap-max",
+        "50%ram",
+        "--dry-run",
+        "--",
+        "cargo",
+        "build",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(r"-pMemorySwapMax=\d+[KMG] ").unwrap());
+}
+
+#[test]
+fn test_memory_high_share_of_ram_limit() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "-m",
+        "50%ram",
+        "--memory-high",
+        "50%",
+        "--dry-run",
+        "--",
+        "cargo",
+        "build",
+    ]);
+
+    // Half of the resolved limit, not 50% of the whole machine.
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(r"-pMemoryHigh=\d+[KMG] ").unwrap())
+        .stdout(predicate::str::contains("-pMemoryHigh=50%").not());
+}
+
+#[test]
+fn test_profile_bad_size_rejected() {
+    let config = common::create_config_dir("[[profile]]\nname = \"big\"\nmemory_limit = \"8GB\"\n");
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args(["--profile", "big", "--dry-run", "--", "cargo", "build"]);
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("profile big"))
+        .stderr(predicate::str::contains(
+            "bad memory_limit: invalid size: 8GB",
+        ));
+}
+
+#[test]
+fn test_profile_share_of_ram() {
+    let config =
+        common::create_config_dir("[[profile]]\nname = \"big\"\nmemory_limit = \"25%ram\"\n");
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args(["--profile", "big", "--dry-run", "--", "cargo", "build"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(r"-pMemoryMax=\d+[KMG] ").unwrap());
+}
+
+#[test]
+fn test_project_file_bad_rate_rejected() {
+    let project = common::create_temp_dir();
+    std::fs::write(
+        project.path().join(".playpen.toml"),
+        "disk_write = \"20 MB/s\"\n",
+    )
+    .unwrap();
+    let config = common::create_temp_dir();
+
+    // Checked by `playpen trust`, before the file can be approved.
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.current_dir(project.path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.arg("trust");
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains(".playpen.toml"))
+        .stderr(predicate::str::contains("bad disk_write: invalid rate"));
+}
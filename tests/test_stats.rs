@@ -0,0 +1,73 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+mod common;
+
+#[test]
+fn test_stats_enables_accounting() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["--stats", "--dry-run", "--", "echo", "hi"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pMemoryAccounting=yes"))
+        .stdout(predicate::str::contains("-pCPUAccounting=yes"))
+        .stdout(predicate::str::contains("-pIOAccounting=yes"))
+        .stdout(predicate::str::contains("-pTasksAccounting=yes"));
+}
+
+#[test]
+fn test_no_stats_no_accounting() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["--dry-run", "--", "echo", "hi"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Accounting").not());
+}
+
+#[test]
+fn test_stats_json_format_enables_accounting() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["--stats=json", "--dry-run", "--", "echo", "hi"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pMemoryAccounting=yes"));
+}
+
+#[test]
+fn test_stats_with_systemd_run_backend_rejected() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--stats",
+        "--backend",
+        "systemd-run",
+        "--dry-run",
+        "--",
+        "echo",
+        "hi",
+    ]);
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("--stats needs --backend dbus"));
+}
+
+#[test]
+fn test_stats_file_requires_stats() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["--stats-file", "out.json", "--dry-run", "--", "echo", "hi"]);
+
+    cmd.assert().failure();
+}
+
+#[test]
+fn test_stats_explained_as_cli() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["--stats", "--explain", "--", "echo", "hi"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(r"MemoryAccounting=yes\s+cli\n").unwrap());
+}
@@ -0,0 +1,157 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::os::unix::fs::symlink;
+
+mod common;
+
+// Bound symlinks whose targets the sandbox would hide. The temporary
+// directories live under /tmp, which `PrivateTmp=` (on by default) hides,
+// standing in for a home directory under `ProtectHome=tmpfs`.
+
+#[test]
+fn test_symlink_target_bound_with_same_mode() {
+    let dir = common::create_temp_dir();
+    let stow = dir.path().join("stowfiles");
+    std::fs::create_dir(&stow).unwrap();
+    std::fs::write(stow.join("settings.json"), "{}").unwrap();
+    let link = dir.path().join("settings.json");
+    symlink("stowfiles/settings.json", &link).unwrap();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--ro",
+        link.to_str().unwrap(),
+        "--dry-run",
+        "--",
+        "echo",
+        "hi",
+    ]);
+
+    let target = std::fs::canonicalize(stow.join("settings.json")).unwrap();
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "-pBindReadOnlyPaths={}",
+            link.display()
+        )))
+        .stdout(predicate::str::contains(format!(
+            "-pBindReadOnlyPaths={}",
+            target.display()
+        )))
+        .stdout(predicate::str::contains("-pBindPaths=").not());
+}
+
+#[test]
+fn test_symlink_chain_followed() {
+    let dir = common::create_temp_dir();
+    let real = dir.path().join("real");
+    std::fs::create_dir(&real).unwrap();
+    let middle = dir.path().join("middle");
+    symlink(&real, &middle).unwrap();
+    let link = dir.path().join("link");
+    symlink(&middle, &link).unwrap();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--rw",
+        link.to_str().unwrap(),
+        "--dry-run",
+        "--",
+        "echo",
+        "hi",
+    ]);
+
+    let canonical = |p: &std::path::Path| {
+        std::fs::canonicalize(p.parent().unwrap())
+            .unwrap()
+            .join(p.file_name().unwrap())
+    };
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "-pBindPaths={}",
+            canonical(&middle).display()
+        )))
+        .stdout(predicate::str::contains(format!(
+            "-pBindPaths={}",
+            canonical(&real).display()
+        )));
+}
+
+#[test]
+fn test_target_not_bound_when_visible() {
+    let dir = common::create_temp_dir();
+    std::fs::create_dir(dir.path().join("real")).unwrap();
+    let link = dir.path().join("link");
+    symlink("real", &link).unwrap();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--private-tmp",
+        "false",
+        "--ro",
+        link.to_str().unwrap(),
+        "--dry-run",
+        "--",
+        "echo",
+        "hi",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("BindReadOnlyPaths").count(1));
+}
+
+#[test]
+fn test_target_under_bound_path_not_bound_again() {
+    let dir = common::create_temp_dir();
+    std::fs::create_dir(dir.path().join("real")).unwrap();
+    let link = dir.path().join("link");
+    symlink("real", &link).unwrap();
+    let parent = std::fs::canonicalize(dir.path()).unwrap();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--ro",
+        parent.to_str().unwrap(),
+        "--ro",
+        link.to_str().unwrap(),
+        "--dry-run",
+        "--",
+        "echo",
+        "hi",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("BindReadOnlyPaths").count(2));
+}
+
+#[test]
+fn test_symlink_target_explained() {
+    let dir = common::create_temp_dir();
+    std::fs::create_dir(dir.path().join("real")).unwrap();
+    let link = dir.path().join("link");
+    symlink("real", &link).unwrap();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--ro",
+        link.to_str().unwrap(),
+        "--explain",
+        "--",
+        "echo",
+        "hi",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Symlink targets bound:"))
+        .stdout(
+            predicate::str::is_match(format!(
+                r"/real\s+bind_ro_paths for {} from cli",
+                common::regex_escape(link.to_str().unwrap())
+            ))
+            .unwrap(),
+        );
+}
@@ -0,0 +1,172 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+mod common;
+
+#[test]
+fn test_timeout_sets_runtime_max() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["--timeout", "30m", "--dry-run", "--", "cargo", "test"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pRuntimeMaxSec=30m"));
+}
+
+#[test]
+fn test_compound_timeout_passed_verbatim() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["--timeout", "1h30m", "--dry-run", "--", "cargo", "test"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pRuntimeMaxSec=1h30m"));
+}
+
+#[test]
+fn test_no_timeout_by_default() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["--dry-run", "--", "cargo", "test"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("RuntimeMaxSec").not());
+}
+
+#[test]
+fn test_invalid_timeout_rejected() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["--timeout", "soon", "--dry-run", "--", "cargo", "test"]);
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid time span: soon"));
+}
+
+#[test]
+fn test_invalid_idle_timeout_rejected() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["--idle-timeout", "5x", "--dry-run", "--", "cargo", "test"]);
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid time span: 5x"));
+}
+
+#[test]
+fn test_profile_timeout() {
+    let config = common::create_config_dir(
+        "[[profile]]\nname = \"slow\"\nextends = \"cargo\"\ntimeout = \"45m\"\n",
+    );
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args(["--profile", "slow", "--dry-run", "--", "cargo", "test"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pRuntimeMaxSec=45m"));
+}
+
+#[test]
+fn test_cli_timeout_overrides_profile() {
+    let config = common::create_config_dir(
+        "[[profile]]\nname = \"slow\"\nextends = \"cargo\"\ntimeout = \"45m\"\n",
+    );
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args([
+        "--profile",
+        "slow",
+        "--timeout",
+        "5m",
+        "--dry-run",
+        "--",
+        "cargo",
+        "test",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pRuntimeMaxSec=5m"))
+        .stdout(predicate::str::contains("45m").not());
+}
+
+#[test]
+fn test_profile_invalid_timeout_rejected() {
+    let config = common::create_config_dir("[[profile]]\nname = \"slow\"\ntimeout = \"forever\"\n");
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args(["--profile", "slow", "--dry-run", "--", "cargo", "test"]);
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("profile slow"))
+        .stderr(predicate::str::contains("bad timeout"));
+}
+
+#[test]
+fn test_project_file_timeout() {
+    let project = common::create_temp_dir();
+    std::fs::write(project.path().join(".playpen.toml"), "timeout = \"20m\"\n").unwrap();
+    let config = common::trust_project_file(project.path());
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.current_dir(project.path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args(["--dry-run", "--", "make"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pRuntimeMaxSec=20m"));
+}
+
+#[test]
+fn test_timeout_explained() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args(["--timeout", "30m", "--explain", "--", "cargo", "test"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(r"RuntimeMaxSec=30m\s+cli").unwrap());
+}
+
+#[test]
+fn test_timeout_allowed_with_systemd_run_backend() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--timeout",
+        "30m",
+        "--backend",
+        "systemd-run",
+        "--dry-run",
+        "--",
+        "cargo",
+        "test",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pRuntimeMaxSec=30m"));
+}
+
+#[test]
+fn test_idle_timeout_with_systemd_run_backend_rejected() {
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.args([
+        "--idle-timeout",
+        "5m",
+        "--backend",
+        "systemd-run",
+        "--dry-run",
+        "--",
+        "cargo",
+        "test",
+    ]);
+
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--idle-timeout needs --backend dbus",
+    ));
+}
@@ -0,0 +1,391 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+mod common;
+
+// User profiles are read from `$XDG_CONFIG_HOME/playpen/profiles.toml`; each
+// test points XDG_CONFIG_HOME at its own temporary directory.
+
+#[test]
+fn test_user_profile_is_usable() {
+    let config = common::create_config_dir(
+        r#"
+[[profile]]
+name = "terraform"
+description = "Terraform plan/apply"
+memory_limit = "768M"
+cpu_quota = "150%"
+protect_home = "tmpfs"
+"#,
+    );
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args([
+        "--profile",
+        "terraform",
+        "--dry-run",
+        "--",
+        "terraform",
+        "plan",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pMemoryMax=768M"))
+        .stdout(predicate::str::contains("-pCPUQuota=150%"))
+        .stdout(predicate::str::contains("-pProtectHome=tmpfs"))
+        // Profiles always bind the working directory.
+        .stdout(predicate::str::contains("-pBindPaths="));
+}
+
+#[test]
+fn test_user_profile_shadows_builtin() {
+    let config = common::create_config_dir(
+        r#"
+[[profile]]
+name = "cargo"
+description = "Monorepo-sized cargo"
+memory_limit = "8G"
+protect_home = "read-only"
+"#,
+    );
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args(["--profile", "cargo", "--dry-run", "--", "cargo", "build"]);
+
+    // The user definition replaces the built-in wholesale, so the built-in's
+    // CPU quota does not leak through.
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pMemoryMax=8G"))
+        .stdout(predicate::str::contains("-pProtectHome=read-only"))
+        .stdout(predicate::str::contains("CPUQuota").not());
+}
+
+#[test]
+fn test_user_profile_paths_expanded() {
+    let temp_home = common::create_temp_dir();
+    std::fs::create_dir(temp_home.path().join(".bazel-cache")).unwrap();
+    let config = common::create_config_dir(
+        r#"
+[[profile]]
+name = "bazel"
+protect_home = "tmpfs"
+rw_paths = ["$HOME/.bazel-cache", "$HOME/.does-not-exist"]
+"#,
+    );
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("HOME", temp_home.path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args(["--profile", "bazel", "--dry-run", "--", "bazel", "build"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "-pBindPaths={}/.bazel-cache",
+            temp_home.path().display()
+        )))
+        // Missing paths are skipped, exactly as for built-in profiles.
+        .stdout(predicate::str::contains(".does-not-exist").not());
+}
+
+#[test]
+fn test_config_under_home_when_xdg_unset() {
+    let temp_home = common::create_temp_dir();
+    std::fs::create_dir_all(temp_home.path().join(".config/playpen")).unwrap();
+    std::fs::write(
+        temp_home.path().join(".config/playpen/profiles.toml"),
+        "[[profile]]\nname = \"tiny\"\nmemory_limit = \"64M\"\n",
+    )
+    .unwrap();
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("HOME", temp_home.path());
+    cmd.env_remove("XDG_CONFIG_HOME");
+    cmd.args(["--profile", "tiny", "--dry-run", "--", "true"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pMemoryMax=64M"));
+}
+
+#[test]
+fn test_unknown_profile_lists_user_profiles() {
+    let config = common::create_config_dir(
+        r#"
+[[profile]]
+name = "terraform"
+description = "Terraform plan/apply"
+"#,
+    );
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args(["--profile", "nope", "--dry-run", "--", "true"]);
+
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains("cargo"))
+        .stderr(predicate::str::contains("terraform"));
+}
+
+#[test]
+fn test_invalid_profile_file_reports_path() {
+    let config = common::create_config_dir("[[profile]]\nname = \"x\"\nmemroy_limit = \"1G\"\n");
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args(["--dry-run", "--", "true"]);
+
+    // Unknown fields are rejected rather than silently ignored, and the
+    // error names the offending file.
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("playpen/profiles.toml"))
+        .stderr(predicate::str::contains("memroy_limit"));
+}
+
+// ============ extends ============
+
+#[test]
+fn test_extends_overrides_only_set_fields() {
+    let temp_home = common::create_temp_dir();
+    std::fs::create_dir(temp_home.path().join(".cargo")).unwrap();
+    std::fs::create_dir(temp_home.path().join("pgsock")).unwrap();
+    let config = common::create_config_dir(
+        r#"
+[[profile]]
+name = "cargo-pg"
+extends = "cargo"
+memory_limit = "3G"
+rw_paths = ["$HOME/pgsock"]
+"#,
+    );
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("HOME", temp_home.path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args(["--profile", "cargo-pg", "--dry-run", "--", "cargo", "test"]);
+
+    let home = temp_home.path().display().to_string();
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pMemoryMax=3G"))
+        // Inherited from cargo.
+        .stdout(predicate::str::contains("-pCPUQuota=300%"))
+        .stdout(predicate::str::contains("-pProtectHome=tmpfs"))
+        // Paths add up: the parent's and the child's.
+        .stdout(predicate::str::contains(format!(
+            "-pBindPaths={}/.cargo",
+            home
+        )))
+        .stdout(predicate::str::contains(format!(
+            "-pBindPaths={}/pgsock",
+            home
+        )));
+}
+
+#[test]
+fn test_extends_own_name_builds_on_builtin() {
+    let config = common::create_config_dir(
+        r#"
+[[profile]]
+name = "cargo"
+extends = "cargo"
+memory_limit = "8G"
+"#,
+    );
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args(["--profile", "cargo", "--dry-run", "--", "cargo", "build"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pMemoryMax=8G"))
+        .stdout(predicate::str::contains("-pCPUQuota=300%"));
+}
+
+#[test]
+fn test_extends_chain() {
+    let config = common::create_config_dir(
+        r#"
+[[profile]]
+name = "b"
+extends = "a"
+cpu_quota = "50%"
+
+[[profile]]
+name = "a"
+extends = "make"
+memory_limit = "1G"
+"#,
+    );
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args(["--profile", "b", "--dry-run", "--", "make"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pMemoryMax=1G"))
+        .stdout(predicate::str::contains("-pCPUQuota=50%"))
+        .stdout(predicate::str::contains("-pMemorySwapMax=0"));
+}
+
+#[test]
+fn test_extends_unknown_profile_error() {
+    let config = common::create_config_dir("[[profile]]\nname = \"x\"\nextends = \"nope\"\n");
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args(["--dry-run", "--", "true"]);
+
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "profile 'x' extends unknown profile 'nope'",
+    ));
+}
+
+#[test]
+fn test_extends_cycle_error() {
+    let config = common::create_config_dir(
+        "[[profile]]\nname = \"x\"\nextends = \"y\"\n\n[[profile]]\nname = \"y\"\nextends = \"x\"\n",
+    );
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args(["--dry-run", "--", "true"]);
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("profile inheritance cycle"));
+}
+
+#[test]
+fn test_user_profile_disk_limits() {
+    let config = common::create_config_dir(
+        r#"
+[[profile]]
+name = "ingest"
+disk_read = "40M"
+disk_write = "10M"
+"#,
+    );
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args(["--profile", "ingest", "--dry-run", "--", "echo", "hi"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(r"-pIOReadBandwidthMax=\S+ 40M").unwrap())
+        .stdout(predicate::str::is_match(r"-pIOWriteBandwidthMax=\S+ 10M").unwrap());
+}
+
+#[test]
+fn test_cli_disk_limit_overrides_profile() {
+    let config = common::create_config_dir(
+        r#"
+[[profile]]
+name = "ingest"
+disk_read = "40M"
+disk_write = "10M"
+"#,
+    );
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args([
+        "--profile",
+        "ingest",
+        "--disk-write",
+        "5M",
+        "--dry-run",
+        "--",
+        "echo",
+        "hi",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(r"-pIOReadBandwidthMax=\S+ 40M").unwrap())
+        .stdout(predicate::str::is_match(r"-pIOWriteBandwidthMax=\S+ 5M").unwrap());
+}
+
+#[test]
+fn test_user_profile_cpu_pinning_and_priority() {
+    let config = common::create_config_dir(
+        "[[profile]]\nname = \"bench\"\nallowed_cpus = \"2-3\"\ncpu_weight = \"idle\"\nnice = 15\nio_scheduling_class = \"idle\"\n",
+    );
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args(["--profile", "bench", "--dry-run", "--", "./bench"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-pAllowedCPUs=2-3"))
+        .stdout(predicate::str::contains("-pCPUWeight=idle"))
+        .stdout(predicate::str::contains("-pNice=15"))
+        .stdout(predicate::str::contains("-pIOSchedulingClass=idle"));
+}
+
+#[test]
+fn test_user_profile_bad_priority_rejected() {
+    for (field, expected) in [
+        ("nice = 40", "bad nice"),
+        ("cpu_weight = \"heavy\"", "bad cpu_weight"),
+        ("allowed_cpus = \"x\"", "bad allowed_cpus"),
+        ("io_scheduling_class = \"low\"", "failed to parse"),
+    ] {
+        let config =
+            common::create_config_dir(&format!("[[profile]]\nname = \"bench\"\n{}\n", field));
+
+        let mut cmd = Command::new(common::get_playpen_path());
+        cmd.env("XDG_CONFIG_HOME", config.path());
+        cmd.args(["--profile", "bench", "--dry-run", "--", "./bench"]);
+
+        cmd.assert()
+            .failure()
+            .stderr(predicate::str::contains(expected));
+    }
+}
+
+#[test]
+fn test_user_profile_protect_none_is_unset() {
+    let config = common::create_config_dir(
+        "[[profile]]\nname = \"open\"\nprotect_home = \"none\"\nprotect_system = \"none\"\n",
+    );
+
+    let mut cmd = Command::new(common::get_playpen_path());
+    cmd.env("XDG_CONFIG_HOME", config.path());
+    cmd.args(["--profile", "open", "--dry-run", "--", "ls"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("ProtectHome").not())
+        .stdout(predicate::str::contains("ProtectSystem").not());
+}
+
+#[test]
+fn test_user_profile_bad_protect_rejected() {
+    for (field, expected) in [
+        ("protect_home = \"bogus\"", "bad protect_home"),
+        ("protect_system = \"read-only\"", "bad protect_system"),
+    ] {
+        let config =
+            common::create_config_dir(&format!("[[profile]]\nname = \"open\"\n{}\n", field));
+
+        let mut cmd = Command::new(common::get_playpen_path());
+        cmd.env("XDG_CONFIG_HOME", config.path());
+        cmd.args(["--profile", "open", "--dry-run", "--", "ls"]);
+
+        cmd.assert()
+            .failure()
+            .stderr(predicate::str::contains(expected));
+    }
+}